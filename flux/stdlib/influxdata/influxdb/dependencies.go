@@ -10,11 +10,15 @@ import (
 	"github.com/influxdata/flux/dependencies/secret"
 	"github.com/influxdata/flux/dependencies/url"
 	"github.com/influxdata/influxdb/coordinator"
+	"github.com/influxdata/influxql"
 )
 
 type key int
 
-const dependenciesKey key = iota
+const (
+	dependenciesKey key = iota
+	databaseCheckKey
+)
 
 type PointsWriter interface {
 	WritePointsInto(request *coordinator.IntoWriteRequest) error
@@ -52,6 +56,22 @@ func GetStorageDependencies(ctx context.Context) StorageDependencies {
 	return ctx.Value(dependenciesKey).(StorageDependencies)
 }
 
+// DatabaseCheck is called for each database a query reads from or writes to,
+// after the user has been authorized. Returning an error fails the query.
+type DatabaseCheck func(db string, privilege influxql.Privilege) error
+
+// NewContextWithDatabaseCheck returns a new context with check added.
+func NewContextWithDatabaseCheck(ctx context.Context, check DatabaseCheck) context.Context {
+	return context.WithValue(ctx, databaseCheckKey, check)
+}
+
+// DatabaseCheckFromContext returns the DatabaseCheck associated with ctx or
+// nil if none has been assigned.
+func DatabaseCheckFromContext(ctx context.Context) DatabaseCheck {
+	check, _ := ctx.Value(databaseCheckKey).(DatabaseCheck)
+	return check
+}
+
 type Dependencies struct {
 	StorageDeps StorageDependencies
 	FluxDeps    flux.Dependency
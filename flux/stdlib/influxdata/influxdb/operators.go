@@ -93,6 +93,11 @@ func lookupDatabase(ctx context.Context, bucketName string, deps StorageDependen
 			return "", "", err
 		}
 	}
+	if check := DatabaseCheckFromContext(ctx); check != nil {
+		if err := check(db, privilege); err != nil {
+			return "", "", err
+		}
+	}
 	if rp == "" {
 		rp = di.DefaultRetentionPolicy
 	}
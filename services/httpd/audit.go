@@ -0,0 +1,215 @@
+package httpd
+
+import (
+	"context"
+	"encoding/json"
+	"net"
+	"net/http"
+	"os"
+	"sync"
+	"time"
+
+	"github.com/influxdata/influxql"
+	"go.uber.org/zap"
+)
+
+// auditEntry is a single line of the audit log.
+type auditEntry struct {
+	Time      time.Time `json:"time"`
+	RequestID string    `json:"request_id,omitempty"`
+	User      string    `json:"user,omitempty"`
+	Addr      string    `json:"addr"`
+	Operation string    `json:"op"`
+	Database  string    `json:"db,omitempty"`
+	Status    int       `json:"status"`
+	Outcome   string    `json:"outcome"`
+
+	// audited is set when the request must be recorded regardless of its
+	// outcome.
+	audited bool
+}
+
+type auditContextKey struct{}
+
+// AuditLog writes one JSON line per mutating or administrative request, and
+// per request rejected by authentication or authorization, to a file.
+type AuditLog struct {
+	mu sync.Mutex
+	f  *os.File
+}
+
+// OpenAuditLog opens the audit log at path for appending.
+func OpenAuditLog(path string) (*AuditLog, error) {
+	f, err := os.OpenFile(path, os.O_WRONLY|os.O_APPEND|os.O_CREATE, 0600)
+	if err != nil {
+		return nil, err
+	}
+	return &AuditLog{f: f}, nil
+}
+
+func (l *AuditLog) log(e *auditEntry) error {
+	b, err := json.Marshal(e)
+	if err != nil {
+		return err
+	}
+	b = append(b, '\n')
+
+	l.mu.Lock()
+	defer l.mu.Unlock()
+	_, err = l.f.Write(b)
+	return err
+}
+
+// Close closes the underlying file.
+func (l *AuditLog) Close() error {
+	l.mu.Lock()
+	defer l.mu.Unlock()
+	return l.f.Close()
+}
+
+// audit records the request in the audit log if it is mutating or
+// administrative, or if it was rejected with a 401 or 403. Handlers that
+// serve both reads and writes, such as /query, decide for themselves by
+// calling auditRequest.
+func (h *Handler) audit(inner http.Handler, name string) http.Handler {
+	return http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		// The log is closed once the server has shut down, so hold on to it for
+		// the whole request.
+		auditLog := h.currentAuditLog()
+		if auditLog == nil {
+			inner.ServeHTTP(w, r)
+			return
+		}
+
+		e := &auditEntry{
+			Time:      time.Now().UTC(),
+			RequestID: r.Header.Get("Request-Id"),
+			Operation: name,
+			audited:   auditedByDefault(name, r.Method),
+		}
+		l := &responseLogger{w: w}
+		inner.ServeHTTP(l, r.WithContext(context.WithValue(r.Context(), auditContextKey{}, e)))
+
+		status := l.Status()
+		if !e.audited && status != http.StatusUnauthorized && status != http.StatusForbidden {
+			return
+		}
+
+		if e.User == "" {
+			e.User = parseUsername(r)
+		}
+		if e.Database == "" {
+			q := r.URL.Query()
+			if e.Database = q.Get("db"); e.Database == "" {
+				e.Database = q.Get("bucket")
+			}
+		}
+		if host, _, err := net.SplitHostPort(r.RemoteAddr); err == nil {
+			e.Addr = host
+		} else {
+			e.Addr = r.RemoteAddr
+		}
+		e.Status = status
+		switch {
+		case status/100 == 2:
+			e.Outcome = "success"
+		case status == http.StatusUnauthorized || status == http.StatusForbidden:
+			e.Outcome = "denied"
+		default:
+			e.Outcome = "failure"
+		}
+
+		if err := auditLog.log(e); err != nil {
+			h.Logger.Error("Failed to write audit log entry", zap.Error(err))
+		}
+	})
+}
+
+// currentAuditLog returns the audit log, or nil if it is disabled or closed.
+func (h *Handler) currentAuditLog() *AuditLog {
+	h.auditMu.RLock()
+	defer h.auditMu.RUnlock()
+	return h.auditLog
+}
+
+// auditedByDefault reports whether requests to a route are recorded
+// regardless of what they do.
+func auditedByDefault(name, method string) bool {
+	switch name {
+	case "query", "flux-read", "prometheus-read":
+		return false
+	}
+
+	switch method {
+	case http.MethodGet, http.MethodHead, http.MethodOptions:
+		return false
+	}
+	return true
+}
+
+// auditEntryFrom returns the audit entry for the request, or nil if the audit
+// log is disabled.
+func auditEntryFrom(r *http.Request) *auditEntry {
+	e, _ := r.Context().Value(auditContextKey{}).(*auditEntry)
+	return e
+}
+
+// auditQuery marks a query for the audit log if any of its statements
+// require write or admin privileges. The entry's database is the db parameter
+// or, without one, the first database the query names.
+func auditQuery(r *http.Request, q *influxql.Query, db string) {
+	e := auditEntryFrom(r)
+	if e == nil {
+		return
+	}
+
+	if e.Database = db; e.Database == "" {
+		e.Database = statementDatabase(q)
+	}
+
+	for _, stmt := range q.Statements {
+		privs, err := stmt.RequiredPrivileges()
+		if err != nil {
+			e.audited = true
+			return
+		}
+		for _, p := range privs {
+			if p.Admin || p.Privilege == influxql.WritePrivilege || p.Privilege == influxql.AllPrivileges {
+				e.audited = true
+				return
+			}
+		}
+	}
+}
+
+// statementDatabase returns the first database named by the statements of the
+// query, either as the subject of a database statement or by a fully
+// qualified measurement.
+func statementDatabase(q *influxql.Query) string {
+	for _, stmt := range q.Statements {
+		switch stmt := stmt.(type) {
+		case *influxql.CreateDatabaseStatement:
+			return stmt.Name
+		case *influxql.DropDatabaseStatement:
+			return stmt.Name
+		}
+	}
+
+	if dbs := queryDatabases(q, ""); len(dbs) > 0 {
+		return dbs[0]
+	}
+	return ""
+}
+
+// auditFluxWrite marks a Flux query that writes to db for the audit log.
+func auditFluxWrite(r *http.Request, db string) {
+	e := auditEntryFrom(r)
+	if e == nil {
+		return
+	}
+
+	e.audited = true
+	if e.Database == "" {
+		e.Database = db
+	}
+}
@@ -0,0 +1,208 @@
+package httpd
+
+import (
+	"compress/gzip"
+	"io"
+	"net/http"
+	"strconv"
+	"strings"
+	"sync"
+
+	"github.com/klauspost/compress/zstd"
+)
+
+// Content encodings supported for compressed responses.
+const (
+	encodingGzip = "gzip"
+	encodingZstd = "zstd"
+)
+
+type lazyCompressResponseWriter struct {
+	io.Writer
+	http.ResponseWriter
+	http.Flusher
+	http.CloseNotifier
+	encoding    string
+	wroteHeader bool
+}
+
+// compressFilter determines if the client can accept compressed responses, and encodes accordingly.
+func compressFilter(inner http.Handler) http.Handler {
+	return http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		encoding := acceptedEncoding(r.Header.Get("Accept-Encoding"))
+		if encoding == "" {
+			inner.ServeHTTP(w, r)
+			return
+		}
+
+		cw := &lazyCompressResponseWriter{ResponseWriter: w, Writer: w, encoding: encoding}
+
+		if f, ok := w.(http.Flusher); ok {
+			cw.Flusher = f
+		}
+
+		if cn, ok := w.(http.CloseNotifier); ok {
+			cw.CloseNotifier = cn
+		}
+
+		defer cw.Close()
+
+		inner.ServeHTTP(cw, r)
+	})
+}
+
+// acceptedEncoding returns the response encoding to use for the given
+// Accept-Encoding header, or an empty string if the response should not be
+// compressed. The encoding with the highest quality value is used, and zstd
+// is preferred over gzip when they are equal since it is considerably cheaper
+// to produce for the same ratio. The response is not compressed if the client
+// prefers identity, or if no supported encoding is acceptable.
+func acceptedEncoding(header string) string {
+	zstdQ, gzipQ, identityQ, anyQ := -1.0, -1.0, -1.0, -1.0
+	for _, part := range strings.Split(header, ",") {
+		coding, params, _ := strings.Cut(part, ";")
+		q, ok := parseQuality(params)
+		if !ok {
+			continue
+		}
+
+		switch strings.ToLower(strings.TrimSpace(coding)) {
+		case encodingZstd:
+			zstdQ = q
+		case encodingGzip:
+			gzipQ = q
+		case "identity":
+			identityQ = q
+		case "*":
+			anyQ = q
+		}
+	}
+
+	// The wildcard applies to every encoding not listed by name.
+	if anyQ >= 0 {
+		if zstdQ < 0 {
+			zstdQ = anyQ
+		}
+		if gzipQ < 0 {
+			gzipQ = anyQ
+		}
+		if identityQ < 0 {
+			identityQ = anyQ
+		}
+	}
+
+	encoding, q := encodingZstd, zstdQ
+	if gzipQ > q {
+		encoding, q = encodingGzip, gzipQ
+	}
+	if q <= 0 || identityQ > q {
+		return ""
+	}
+	return encoding
+}
+
+// parseQuality returns the quality value in the parameters of an
+// Accept-Encoding element. The quality defaults to 1 and false is returned if
+// it is not valid.
+func parseQuality(params string) (float64, bool) {
+	for _, param := range strings.Split(params, ";") {
+		key, value, _ := strings.Cut(param, "=")
+		if !strings.EqualFold(strings.TrimSpace(key), "q") {
+			continue
+		}
+
+		q, err := strconv.ParseFloat(strings.TrimSpace(value), 64)
+		if err != nil || q < 0 || q > 1 {
+			return 0, false
+		}
+		return q, true
+	}
+	return 1, true
+}
+
+func (w *lazyCompressResponseWriter) WriteHeader(code int) {
+	if w.wroteHeader {
+		return
+	}
+
+	w.wroteHeader = true
+	if code == http.StatusOK {
+		w.Header().Set("Content-Encoding", w.encoding)
+		switch w.encoding {
+		case encodingZstd:
+			w.Writer = getZstdWriter(w.Writer)
+		default:
+			w.Writer = getGzipWriter(w.Writer)
+		}
+	}
+
+	w.ResponseWriter.WriteHeader(code)
+}
+
+func (w *lazyCompressResponseWriter) Write(p []byte) (int, error) {
+	if !w.wroteHeader {
+		w.WriteHeader(http.StatusOK)
+	}
+	return w.Writer.Write(p)
+}
+
+func (w *lazyCompressResponseWriter) Flush() {
+	// Flush the compressor, if any, so chunked responses are streamed.
+	if f, ok := w.Writer.(interface {
+		Flush() error
+	}); ok {
+		f.Flush()
+	}
+
+	// Flush the HTTP response
+	if w.Flusher != nil {
+		w.Flusher.Flush()
+	}
+}
+
+func (w *lazyCompressResponseWriter) Close() error {
+	switch cw := w.Writer.(type) {
+	case *gzip.Writer:
+		putGzipWriter(cw)
+	case *zstd.Encoder:
+		putZstdWriter(cw)
+	}
+
+	return nil
+}
+
+var gzipWriterPool = sync.Pool{
+	New: func() interface{} {
+		return gzip.NewWriter(nil)
+	},
+}
+
+func getGzipWriter(w io.Writer) *gzip.Writer {
+	gz := gzipWriterPool.Get().(*gzip.Writer)
+	gz.Reset(w)
+	return gz
+}
+
+func putGzipWriter(gz *gzip.Writer) {
+	gz.Close()
+	gzipWriterPool.Put(gz)
+}
+
+var zstdWriterPool = sync.Pool{
+	New: func() interface{} {
+		// Creating an encoder with a nil writer and valid options cannot fail.
+		enc, _ := zstd.NewWriter(nil, zstd.WithEncoderConcurrency(1))
+		return enc
+	},
+}
+
+func getZstdWriter(w io.Writer) *zstd.Encoder {
+	enc := zstdWriterPool.Get().(*zstd.Encoder)
+	enc.Reset(w)
+	return enc
+}
+
+func putZstdWriter(enc *zstd.Encoder) {
+	enc.Close()
+	zstdWriterPool.Put(enc)
+}
@@ -28,6 +28,12 @@ const (
 
 	// DefaultEnqueuedWriteTimeout is the maximum time a write request can wait to be processed.
 	DefaultEnqueuedWriteTimeout = 30 * time.Second
+
+	// DefaultEnrichmentRefresh is the default interval at which the enrichment file is checked for changes.
+	DefaultEnrichmentRefresh = time.Minute
+
+	// DefaultDrainTimeout is the default time allowed for in-flight requests to finish on shutdown.
+	DefaultDrainTimeout = 10 * time.Second
 )
 
 // Config represents a configuration for a HTTP service.
@@ -61,9 +67,17 @@ type Config struct {
 	MaxBodySize             int               `toml:"max-body-size"`
 	AccessLogPath           string            `toml:"access-log-path"`
 	AccessLogStatusFilters  []StatusFilter    `toml:"access-log-status-filters"`
+	AuditLogPath            string            `toml:"audit-log-path"`
+	DrainTimeout            toml.Duration     `toml:"drain-timeout"`
 	MaxConcurrentWriteLimit int               `toml:"max-concurrent-write-limit"`
 	MaxEnqueuedWriteLimit   int               `toml:"max-enqueued-write-limit"`
 	EnqueuedWriteTimeout    time.Duration     `toml:"enqueued-write-timeout"`
+	DatabaseWriteRateLimit  toml.Size         `toml:"database-write-rate-limit"`
+	DatabaseQueryRateLimit  int               `toml:"database-query-rate-limit"`
+	EnrichmentFile          string            `toml:"enrichment-file"`
+	EnrichmentRefresh       toml.Duration     `toml:"enrichment-refresh-interval"`
+	MaxWriteTimePast        toml.Duration     `toml:"max-write-time-past"`
+	MaxWriteTimeFuture      toml.Duration     `toml:"max-write-time-future"`
 	TLS                     *tls.Config       `toml:"-"`
 }
 
@@ -90,6 +104,8 @@ func NewConfig() Config {
 		BindSocket:            DefaultBindSocket,
 		MaxBodySize:           DefaultMaxBodySize,
 		EnqueuedWriteTimeout:  DefaultEnqueuedWriteTimeout,
+		EnrichmentRefresh:     toml.Duration(DefaultEnrichmentRefresh),
+		DrainTimeout:          toml.Duration(DefaultDrainTimeout),
 	}
 }
 
@@ -2,6 +2,7 @@ package httpd_test
 
 import (
 	"testing"
+	"time"
 
 	"github.com/BurntSushi/toml"
 	"github.com/influxdata/influxdb/services/httpd"
@@ -21,6 +22,14 @@ https-certificate = "/dev/null"
 unix-socket-enabled = true
 bind-socket = "/var/run/influxdb.sock"
 max-body-size = 100
+database-write-rate-limit = "1m"
+database-query-rate-limit = 20
+enrichment-file = "/etc/influxdb/enrichment.csv"
+enrichment-refresh-interval = "5m"
+audit-log-path = "/var/log/influxdb/audit.log"
+drain-timeout = "30s"
+max-write-time-past = "168h"
+max-write-time-future = "1h"
 `, &c); err != nil {
 		t.Fatal(err)
 	}
@@ -46,6 +55,22 @@ max-body-size = 100
 		t.Fatalf("unexpected bind unix socket: %v", c.BindSocket)
 	} else if c.MaxBodySize != 100 {
 		t.Fatalf("unexpected max-body-size: %v", c.MaxBodySize)
+	} else if c.DatabaseWriteRateLimit != 1<<20 {
+		t.Fatalf("unexpected database-write-rate-limit: %v", c.DatabaseWriteRateLimit)
+	} else if c.DatabaseQueryRateLimit != 20 {
+		t.Fatalf("unexpected database-query-rate-limit: %v", c.DatabaseQueryRateLimit)
+	} else if c.EnrichmentFile != "/etc/influxdb/enrichment.csv" {
+		t.Fatalf("unexpected enrichment-file: %v", c.EnrichmentFile)
+	} else if time.Duration(c.EnrichmentRefresh) != 5*time.Minute {
+		t.Fatalf("unexpected enrichment-refresh-interval: %v", c.EnrichmentRefresh)
+	} else if c.AuditLogPath != "/var/log/influxdb/audit.log" {
+		t.Fatalf("unexpected audit-log-path: %v", c.AuditLogPath)
+	} else if time.Duration(c.DrainTimeout) != 30*time.Second {
+		t.Fatalf("unexpected drain-timeout: %v", c.DrainTimeout)
+	} else if time.Duration(c.MaxWriteTimePast) != 168*time.Hour {
+		t.Fatalf("unexpected max-write-time-past: %v", c.MaxWriteTimePast)
+	} else if time.Duration(c.MaxWriteTimeFuture) != time.Hour {
+		t.Fatalf("unexpected max-write-time-future: %v", c.MaxWriteTimeFuture)
 	}
 }
 
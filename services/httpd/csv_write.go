@@ -0,0 +1,194 @@
+package httpd
+
+import (
+	"bytes"
+	"encoding/csv"
+	"errors"
+	"fmt"
+	"io"
+	"mime"
+	"net/http"
+	"net/url"
+	"strconv"
+	"strings"
+	"time"
+
+	"github.com/influxdata/influxdb/models"
+)
+
+// isCSVWrite reports whether the body of a write request is CSV rather than
+// line protocol.
+func isCSVWrite(r *http.Request) bool {
+	mt, _, err := mime.ParseMediaType(r.Header.Get("Content-Type"))
+	return err == nil && mt == "text/csv"
+}
+
+// csvMapping describes how the columns of a CSV write map onto points. It is
+// read from the query parameters of the write request:
+//
+//	measurement  the measurement to write to (required)
+//	tags         comma-separated columns written as tags
+//	fields       comma-separated columns written as fields; by default every
+//	             column that is not a tag or the time column
+//	time         the timestamp column, "time" by default
+//
+// Timestamps may be integers in the request's precision or RFC3339 strings.
+// Rows without a timestamp are written at the server's time.
+type csvMapping struct {
+	measurement string
+	timeColumn  string
+	tags        map[string]bool
+	fields      map[string]bool
+}
+
+func newCSVMapping(q url.Values) (*csvMapping, error) {
+	m := &csvMapping{
+		measurement: q.Get("measurement"),
+		timeColumn:  q.Get("time"),
+		tags:        csvColumnSet(q.Get("tags")),
+		fields:      csvColumnSet(q.Get("fields")),
+	}
+	if m.measurement == "" {
+		return nil, errors.New("measurement is required for CSV writes")
+	}
+	if m.timeColumn == "" {
+		m.timeColumn = "time"
+	}
+	return m, nil
+}
+
+func csvColumnSet(s string) map[string]bool {
+	if s == "" {
+		return nil
+	}
+	set := make(map[string]bool)
+	for _, name := range strings.Split(s, ",") {
+		set[strings.TrimSpace(name)] = true
+	}
+	return set
+}
+
+// isField reports whether the column is written as a field.
+func (m *csvMapping) isField(column string) bool {
+	if m.fields != nil {
+		return m.fields[column]
+	}
+	return column != m.timeColumn && !m.tags[column]
+}
+
+// parseCSVPoints converts CSV rows into points. The first row is a header
+// naming the columns. Like models.ParsePointsWithPrecision, rows that cannot
+// be converted are described in the returned error while the remaining rows
+// are still returned.
+func parseCSVPoints(buf []byte, m *csvMapping, defaultTime time.Time, precision string) ([]models.Point, error) {
+	cr := csv.NewReader(bytes.NewReader(buf))
+	cr.ReuseRecord = true
+
+	header, err := cr.Read()
+	if err == io.EOF {
+		return nil, io.EOF
+	} else if err != nil {
+		return nil, err
+	}
+	header = append([]string(nil), header...)
+
+	var (
+		points []models.Point
+		failed []string
+	)
+	for row := 2; ; row++ {
+		record, err := cr.Read()
+		if err == io.EOF {
+			break
+		} else if errors.Is(err, csv.ErrFieldCount) {
+			failed = append(failed, fmt.Sprintf("unable to parse row %d: %v", row, err))
+			continue
+		} else if err != nil {
+			// A malformed row may leave the reader out of sync, so stop here.
+			failed = append(failed, err.Error())
+			break
+		}
+
+		pt, err := m.point(header, record, defaultTime, precision)
+		if err != nil {
+			failed = append(failed, fmt.Sprintf("unable to parse row %d: %v", row, err))
+			continue
+		}
+		points = append(points, pt)
+	}
+
+	if len(failed) > 0 {
+		return points, fmt.Errorf("%s", strings.Join(failed, "\n"))
+	}
+	return points, nil
+}
+
+// point converts a single CSV record into a point.
+func (m *csvMapping) point(header, record []string, defaultTime time.Time, precision string) (models.Point, error) {
+	t := defaultTime
+	tags := make(map[string]string)
+	fields := make(models.Fields)
+	for i, value := range record {
+		column := header[i]
+		switch {
+		case value == "":
+			continue
+		case column == m.timeColumn:
+			ts, err := parseCSVTime(value, precision)
+			if err != nil {
+				return nil, err
+			}
+			t = ts
+		case m.tags[column]:
+			tags[column] = value
+		case m.isField(column):
+			fields[column] = parseCSVFieldValue(value)
+		}
+	}
+
+	if len(fields) == 0 {
+		return nil, errors.New("no fields")
+	}
+	return models.NewPoint(m.measurement, models.NewTags(tags), fields, t)
+}
+
+// parseCSVTime parses an integer timestamp in the given precision or an
+// RFC3339 timestamp.
+func parseCSVTime(s, precision string) (time.Time, error) {
+	if n, err := strconv.ParseInt(s, 10, 64); err == nil {
+		return models.SafeCalcTime(n, precision)
+	}
+
+	t, err := time.Parse(time.RFC3339Nano, s)
+	if err != nil {
+		return time.Time{}, fmt.Errorf("invalid timestamp %q", s)
+	}
+	return t, models.CheckTime(t)
+}
+
+// parseCSVFieldValue infers the type of a field value. Numbers are always
+// written as floats, as in line protocol, so that a column does not conflict
+// with itself when some rows hold whole numbers. Only finite decimal numbers
+// and true or false are converted, so that text such as "NaN", "inf" or "T"
+// stays a string.
+func parseCSVFieldValue(s string) interface{} {
+	if isCSVDecimal(s) {
+		if f, err := strconv.ParseFloat(s, 64); err == nil {
+			return f
+		}
+	}
+	switch {
+	case strings.EqualFold(s, "true"):
+		return true
+	case strings.EqualFold(s, "false"):
+		return false
+	}
+	return s
+}
+
+// isCSVDecimal reports whether s only holds the characters of a decimal
+// number, which excludes the hex, infinite and NaN forms accepted by
+// strconv.ParseFloat.
+func isCSVDecimal(s string) bool {
+	return s != "" && strings.Trim(s, "0123456789.eE+-") == ""
+}
@@ -0,0 +1,95 @@
+package httpd
+
+import (
+	"context"
+	"fmt"
+	"math"
+	"net/http"
+	"strconv"
+	"time"
+)
+
+// requestDeadline attaches the deadline requested by the client, if any, to
+// the request context so that work done on behalf of the request is abandoned
+// once the client has given up on it. Requests whose deadline has already
+// passed are rejected without being processed.
+func (h *Handler) requestDeadline(inner http.Handler) http.Handler {
+	return http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+		now := time.Now()
+		deadline, ok, err := parseRequestDeadline(r.Header, now)
+		if err != nil {
+			h.httpError(w, err.Error(), http.StatusBadRequest)
+			return
+		} else if !ok {
+			inner.ServeHTTP(w, r)
+			return
+		} else if !now.Before(deadline) {
+			h.httpError(w, "request deadline exceeded", http.StatusGatewayTimeout)
+			return
+		}
+
+		ctx, cancel := context.WithDeadline(r.Context(), deadline)
+		defer cancel()
+		inner.ServeHTTP(w, r.WithContext(ctx))
+	})
+}
+
+// parseRequestDeadline returns the deadline requested through either the
+// X-Request-Deadline header, an RFC3339 timestamp, or a gRPC-style
+// Grpc-Timeout header such as "500m". The boolean is false if neither header
+// is set.
+func parseRequestDeadline(header http.Header, now time.Time) (time.Time, bool, error) {
+	if s := header.Get("X-Request-Deadline"); s != "" {
+		t, err := time.Parse(time.RFC3339Nano, s)
+		if err != nil {
+			return time.Time{}, false, fmt.Errorf("invalid X-Request-Deadline %q: expected an RFC3339 timestamp", s)
+		}
+		return t, true, nil
+	}
+
+	if s := header.Get("Grpc-Timeout"); s != "" {
+		d, err := parseGRPCTimeout(s)
+		if err != nil {
+			return time.Time{}, false, err
+		}
+		return now.Add(d), true, nil
+	}
+	return time.Time{}, false, nil
+}
+
+// parseGRPCTimeout parses a timeout in the gRPC wire format: at most eight
+// digits followed by one of the units H, M, S, m, u or n.
+func parseGRPCTimeout(s string) (time.Duration, error) {
+	if len(s) < 2 || len(s) > 9 {
+		return 0, fmt.Errorf("invalid Grpc-Timeout %q", s)
+	}
+
+	var unit time.Duration
+	switch s[len(s)-1] {
+	case 'H':
+		unit = time.Hour
+	case 'M':
+		unit = time.Minute
+	case 'S':
+		unit = time.Second
+	case 'm':
+		unit = time.Millisecond
+	case 'u':
+		unit = time.Microsecond
+	case 'n':
+		unit = time.Nanosecond
+	default:
+		return 0, fmt.Errorf("invalid Grpc-Timeout %q: unknown unit", s)
+	}
+
+	n, err := strconv.ParseInt(s[:len(s)-1], 10, 64)
+	if err != nil || n < 0 {
+		return 0, fmt.Errorf("invalid Grpc-Timeout %q", s)
+	}
+
+	// Eight digits of hours overflows a time.Duration.
+	if n > math.MaxInt64/int64(unit) {
+		return math.MaxInt64, nil
+	}
+	return time.Duration(n) * unit, nil
+}
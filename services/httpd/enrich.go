@@ -0,0 +1,191 @@
+package httpd
+
+import (
+	"encoding/csv"
+	"errors"
+	"fmt"
+	"io"
+	"os"
+	"sync"
+	"time"
+
+	"github.com/influxdata/influxdb/models"
+	"go.uber.org/zap"
+)
+
+// TagEnricher adds tags to written points by looking up the value of a key
+// tag in a reference table loaded from a CSV file. The first column of the
+// header names the key tag and the remaining columns name the tags to add:
+//
+//	host,rack,dc
+//	server01,r12,us-west
+//	server02,r14,us-east
+//
+// Tags already present on a point are never overwritten, and no tags are added
+// to a point if its series key would then exceed models.MaxKeyLength. The file
+// is reloaded when its modification time changes, which is checked at most
+// once per refresh interval. A zero interval disables reloading.
+type TagEnricher struct {
+	path     string
+	interval time.Duration
+
+	mu      sync.Mutex
+	table   *enrichmentTable
+	modTime time.Time
+	checked time.Time
+
+	Logger *zap.Logger
+
+	// now returns the current time. It can be replaced in tests.
+	now func() time.Time
+}
+
+// enrichmentTable is an immutable snapshot of the reference table.
+type enrichmentTable struct {
+	key  []byte
+	tags []string
+	rows map[string][]string
+}
+
+// NewTagEnricher returns a TagEnricher for the CSV file at path. The file is
+// not read until Load or Enrich is called.
+func NewTagEnricher(path string, interval time.Duration) *TagEnricher {
+	return &TagEnricher{
+		path:     path,
+		interval: interval,
+		Logger:   zap.NewNop(),
+		now:      time.Now,
+	}
+}
+
+// Load reads the reference table if it has changed since it was last read.
+func (e *TagEnricher) Load() error {
+	e.mu.Lock()
+	defer e.mu.Unlock()
+	e.checked = e.now()
+	return e.load()
+}
+
+// Enrich adds the tags from the reference table to each point whose key tag
+// matches a row of the table.
+func (e *TagEnricher) Enrich(points []models.Point) {
+	t := e.current()
+	if t == nil {
+		return
+	}
+
+	for _, p := range points {
+		v := p.Tags().Get(t.key)
+		if len(v) == 0 {
+			continue
+		}
+		row, ok := t.rows[string(v)]
+		if !ok {
+			continue
+		}
+
+		var tags models.Tags
+		for i, value := range row {
+			if value == "" || p.HasTag([]byte(t.tags[i])) {
+				continue
+			}
+			if tags == nil {
+				tags = p.Tags().Clone()
+			}
+			tags.SetString(t.tags[i], value)
+		}
+		if tags == nil {
+			continue
+		}
+
+		if key := models.MakeKey(p.Name(), tags); seriesKeyTooLong(p, key) {
+			e.Logger.Debug("skipping write enrichment, max key length exceeded", zap.ByteString("key", p.Key()))
+			continue
+		}
+		p.SetTags(tags)
+	}
+}
+
+// seriesKeyTooLong returns true if key combined with any field of p exceeds
+// models.MaxKeyLength.
+func seriesKeyTooLong(p models.Point, key []byte) bool {
+	// 4 is the length of the tsm1 field key separator.
+	iter := p.FieldIterator()
+	for iter.Next() {
+		if len(key)+4+len(iter.FieldKey()) > models.MaxKeyLength {
+			return true
+		}
+	}
+	return false
+}
+
+// current returns the reference table, reloading it first if the refresh
+// interval has passed. If reloading fails the previous table is kept.
+func (e *TagEnricher) current() *enrichmentTable {
+	e.mu.Lock()
+	defer e.mu.Unlock()
+
+	if now := e.now(); e.checked.IsZero() || (e.interval > 0 && now.Sub(e.checked) >= e.interval) {
+		e.checked = now
+		if err := e.load(); err != nil {
+			e.Logger.Warn("unable to reload write enrichment table", zap.Error(err), zap.String("path", e.path))
+		}
+	}
+	return e.table
+}
+
+// load reads the reference table if the file has changed. The lock must be
+// held.
+func (e *TagEnricher) load() error {
+	fi, err := os.Stat(e.path)
+	if err != nil {
+		return err
+	} else if e.table != nil && fi.ModTime().Equal(e.modTime) {
+		return nil
+	}
+
+	f, err := os.Open(e.path)
+	if err != nil {
+		return err
+	}
+	defer f.Close()
+
+	t, err := readEnrichmentTable(f)
+	if err != nil {
+		return fmt.Errorf("%s: %w", e.path, err)
+	}
+	e.table = t
+	e.modTime = fi.ModTime()
+	return nil
+}
+
+// readEnrichmentTable parses a reference table from CSV.
+func readEnrichmentTable(r io.Reader) (*enrichmentTable, error) {
+	cr := csv.NewReader(r)
+	cr.TrimLeadingSpace = true
+
+	header, err := cr.Read()
+	if err == io.EOF {
+		return nil, errors.New("missing header")
+	} else if err != nil {
+		return nil, err
+	} else if len(header) < 2 {
+		return nil, errors.New("header must name a key tag and at least one tag to add")
+	}
+
+	t := &enrichmentTable{
+		key:  []byte(header[0]),
+		tags: header[1:],
+		rows: make(map[string][]string),
+	}
+	for {
+		record, err := cr.Read()
+		if err == io.EOF {
+			break
+		} else if err != nil {
+			return nil, err
+		}
+		t.rows[record[0]] = record[1:]
+	}
+	return t, nil
+}
@@ -0,0 +1,112 @@
+package httpd
+
+import (
+	"os"
+	"path/filepath"
+	"strings"
+	"testing"
+	"time"
+
+	"github.com/influxdata/influxdb/models"
+)
+
+func TestTagEnricher(t *testing.T) {
+	path := filepath.Join(t.TempDir(), "enrichment.csv")
+	if err := os.WriteFile(path, []byte("host,rack,dc\nserver01,r12,us-west\nserver02,,us-east\n"), 0600); err != nil {
+		t.Fatal(err)
+	}
+
+	now := time.Unix(0, 0)
+	e := NewTagEnricher(path, time.Minute)
+	e.now = func() time.Time { return now }
+
+	points, err := models.ParsePointsString("cpu,host=server01 value=1\ncpu,host=server02,dc=eu value=1\ncpu,host=server03 value=1\nmem value=1")
+	if err != nil {
+		t.Fatal(err)
+	}
+	e.Enrich(points)
+
+	// Missing tags are added, existing tags and empty values are left alone.
+	for i, exp := range []string{
+		"cpu,dc=us-west,host=server01,rack=r12",
+		"cpu,dc=eu,host=server02",
+		"cpu,host=server03",
+		"mem",
+	} {
+		if got := string(points[i].Key()); got != exp {
+			t.Fatalf("unexpected key for point %d: got %s, exp %s", i, got, exp)
+		}
+	}
+
+	// Changes to the file are picked up after the refresh interval.
+	if err := os.WriteFile(path, []byte("host,rack\nserver03,r1\n"), 0600); err != nil {
+		t.Fatal(err)
+	} else if err := os.Chtimes(path, time.Now(), time.Now().Add(time.Hour)); err != nil {
+		t.Fatal(err)
+	}
+
+	points, _ = models.ParsePointsString("cpu,host=server03 value=1")
+	e.Enrich(points)
+	if got := string(points[0].Key()); got != "cpu,host=server03" {
+		t.Fatalf("unexpected key before refresh: %s", got)
+	}
+
+	now = now.Add(time.Minute)
+	e.Enrich(points)
+	if got := string(points[0].Key()); got != "cpu,host=server03,rack=r1" {
+		t.Fatalf("unexpected key after refresh: %s", got)
+	}
+}
+
+func TestTagEnricher_MaxKeyLength(t *testing.T) {
+	path := filepath.Join(t.TempDir(), "enrichment.csv")
+	long := strings.Repeat("x", models.MaxKeyLength)
+	if err := os.WriteFile(path, []byte("host,rack\nserver01,"+long+"\nserver02,r1\n"), 0600); err != nil {
+		t.Fatal(err)
+	}
+
+	e := NewTagEnricher(path, 0)
+	points, err := models.ParsePointsString("cpu,host=server01 value=1\ncpu,host=server02 value=1")
+	if err != nil {
+		t.Fatal(err)
+	}
+	e.Enrich(points)
+
+	// Tags that would make the series key too long are not added.
+	for i, exp := range []string{
+		"cpu,host=server01",
+		"cpu,host=server02,rack=r1",
+	} {
+		if got := string(points[i].Key()); got != exp {
+			t.Fatalf("unexpected key for point %d: got %s, exp %s", i, got, exp)
+		}
+	}
+}
+
+func TestTagEnricher_NoRefresh(t *testing.T) {
+	path := filepath.Join(t.TempDir(), "enrichment.csv")
+	if err := os.WriteFile(path, []byte("host,rack\nserver01,r1\n"), 0600); err != nil {
+		t.Fatal(err)
+	}
+
+	now := time.Unix(0, 0)
+	e := NewTagEnricher(path, 0)
+	e.now = func() time.Time { return now }
+	if err := e.Load(); err != nil {
+		t.Fatal(err)
+	}
+
+	// A zero interval never reloads the file.
+	if err := os.WriteFile(path, []byte("host,rack\nserver01,r2\n"), 0600); err != nil {
+		t.Fatal(err)
+	} else if err := os.Chtimes(path, time.Now(), time.Now().Add(time.Hour)); err != nil {
+		t.Fatal(err)
+	}
+
+	now = now.Add(time.Hour)
+	points, _ := models.ParsePointsString("cpu,host=server01 value=1")
+	e.Enrich(points)
+	if got := string(points[0].Key()); got != "cpu,host=server01,rack=r1" {
+		t.Fatalf("unexpected key: %s", got)
+	}
+}
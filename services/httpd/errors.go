@@ -0,0 +1,87 @@
+package httpd
+
+import "net/http"
+
+// ErrorCode is a stable, machine-readable identifier for the kind of error
+// returned by the HTTP API. It is sent in the X-InfluxDB-Error-Code header of
+// every error response and in the "code" field of JSON error bodies, so that
+// clients can decide whether to retry without matching on error messages.
+type ErrorCode string
+
+const (
+	// ErrorCodeInvalid is returned for malformed requests.
+	ErrorCodeInvalid ErrorCode = "invalid"
+
+	// ErrorCodeParse is returned when a query or line protocol cannot be parsed.
+	ErrorCodeParse ErrorCode = "parse_error"
+
+	// ErrorCodePartialWrite is returned when some points of a write were
+	// rejected, for example because of a series limit. The remaining points
+	// were written and must not be retried.
+	ErrorCodePartialWrite ErrorCode = "partial_write"
+
+	// ErrorCodeSchemaConflict is returned instead of ErrorCodePartialWrite when
+	// points were rejected because a field already exists with another type.
+	// Retrying those points cannot succeed.
+	ErrorCodeSchemaConflict ErrorCode = "schema_conflict"
+
+	// ErrorCodeUnauthorized is returned when authentication fails.
+	ErrorCodeUnauthorized ErrorCode = "unauthorized"
+
+	// ErrorCodeForbidden is returned when the user lacks the required privileges
+	// or the operation has been disabled.
+	ErrorCodeForbidden ErrorCode = "forbidden"
+
+	// ErrorCodeNotFound is returned when a database or resource does not exist.
+	ErrorCodeNotFound ErrorCode = "not_found"
+
+	// ErrorCodeTooLarge is returned when the request body exceeds max-body-size.
+	ErrorCodeTooLarge ErrorCode = "too_large"
+
+	// ErrorCodeRateLimited is returned when a per-database rate limit is
+	// exceeded. The request may be retried after the Retry-After delay.
+	ErrorCodeRateLimited ErrorCode = "rate_limited"
+
+	// ErrorCodeOverloaded is returned when the server cannot accept more work,
+	// such as when the write queue is full. The request may be retried with
+	// backoff.
+	ErrorCodeOverloaded ErrorCode = "overloaded"
+
+	// ErrorCodeDeadlineExceeded is returned when the request deadline passed
+	// before the request could be processed.
+	ErrorCodeDeadlineExceeded ErrorCode = "deadline_exceeded"
+
+	// ErrorCodeNotImplemented is returned for disabled or unsupported endpoints.
+	ErrorCodeNotImplemented ErrorCode = "not_implemented"
+
+	// ErrorCodeInternal is returned for unexpected server errors.
+	ErrorCodeInternal ErrorCode = "internal_error"
+)
+
+// errorCodeForStatus returns the error code for an HTTP status code when no
+// more specific code is known.
+func errorCodeForStatus(status int) ErrorCode {
+	switch status {
+	case http.StatusUnauthorized:
+		return ErrorCodeUnauthorized
+	case http.StatusForbidden:
+		return ErrorCodeForbidden
+	case http.StatusNotFound:
+		return ErrorCodeNotFound
+	case http.StatusRequestEntityTooLarge:
+		return ErrorCodeTooLarge
+	case http.StatusTooManyRequests:
+		return ErrorCodeRateLimited
+	case http.StatusServiceUnavailable:
+		return ErrorCodeOverloaded
+	case http.StatusGatewayTimeout:
+		return ErrorCodeDeadlineExceeded
+	case http.StatusNotImplemented:
+		return ErrorCodeNotImplemented
+	}
+
+	if status >= 500 {
+		return ErrorCodeInternal
+	}
+	return ErrorCodeInvalid
+}
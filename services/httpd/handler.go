@@ -17,6 +17,7 @@ import (
 	"runtime/debug"
 	"strconv"
 	"strings"
+	"sync"
 	"sync/atomic"
 	"time"
 
@@ -24,8 +25,10 @@ import (
 	"github.com/golang-jwt/jwt"
 	"github.com/golang/snappy"
 	"github.com/influxdata/flux"
+	"github.com/influxdata/flux/codes"
 	"github.com/influxdata/flux/lang"
 	"github.com/influxdata/influxdb"
+	influxdb2 "github.com/influxdata/influxdb/flux/stdlib/influxdata/influxdb"
 	"github.com/influxdata/influxdb/logger"
 	"github.com/influxdata/influxdb/models"
 	"github.com/influxdata/influxdb/monitor"
@@ -39,7 +42,7 @@ import (
 	"github.com/influxdata/influxdb/tsdb"
 	"github.com/influxdata/influxdb/uuid"
 	"github.com/influxdata/influxql"
-	"github.com/prometheus/client_golang/prometheus/promhttp"
+	"github.com/klauspost/compress/zstd"
 	"github.com/prometheus/prometheus/prompb"
 	"go.uber.org/zap"
 )
@@ -150,6 +153,15 @@ type Handler struct {
 
 	requestTracker *RequestTracker
 	writeThrottler *Throttler
+	killSwitches   *KillSwitches
+	tagEnricher    *TagEnricher
+	writeWindow    writeTimeWindow
+	auditMu        sync.RWMutex
+	auditLog       *AuditLog
+	draining       int32
+
+	writeRateLimiter *RateLimiter
+	queryRateLimiter *RateLimiter
 }
 
 // NewHandler returns a new instance of handler with routes.
@@ -161,12 +173,32 @@ func NewHandler(c Config) *Handler {
 		CLFLogger:      log.New(os.Stderr, "[httpd] ", 0),
 		stats:          &Statistics{},
 		requestTracker: NewRequestTracker(),
+		killSwitches:   NewKillSwitches(),
 	}
 
 	// Limit the number of concurrent & enqueued write requests.
 	h.writeThrottler = NewThrottler(c.MaxConcurrentWriteLimit, c.MaxEnqueuedWriteLimit)
 	h.writeThrottler.EnqueueTimeout = c.EnqueuedWriteTimeout
 
+	// Limit the rate of writes and queries per database.
+	if c.DatabaseWriteRateLimit > 0 {
+		h.writeRateLimiter = NewRateLimiter(float64(c.DatabaseWriteRateLimit))
+	}
+	if c.DatabaseQueryRateLimit > 0 {
+		h.queryRateLimiter = NewRateLimiter(float64(c.DatabaseQueryRateLimit))
+	}
+
+	// Add tags to written points from a reference table.
+	if c.EnrichmentFile != "" {
+		h.tagEnricher = NewTagEnricher(c.EnrichmentFile, time.Duration(c.EnrichmentRefresh))
+	}
+
+	// Drop points with timestamps too far from the server's clock.
+	h.writeWindow = writeTimeWindow{
+		past:   time.Duration(c.MaxWriteTimePast),
+		future: time.Duration(c.MaxWriteTimeFuture),
+	}
+
 	// Disable the write log if they have been suppressed.
 	writeLogEnabled := c.LogEnabled
 	if c.SuppressWriteLog {
@@ -309,7 +341,27 @@ func NewHandler(c Config) *Handler {
 		},
 		Route{
 			"prometheus-metrics",
-			"GET", "/metrics", false, true, authWrapper(promhttp.Handler().ServeHTTP),
+			"GET", "/metrics", false, true, authWrapper(newMetricsHandler().ServeHTTP),
+		},
+		Route{
+			"kill-switches",
+			"GET", "/api/v1/killswitch", false, true, h.serveKillSwitches,
+		},
+		Route{
+			"set-kill-switch",
+			"POST", "/api/v1/killswitch", false, true, h.serveSetKillSwitch,
+		},
+		Route{
+			"clear-kill-switch",
+			"DELETE", "/api/v1/killswitch", false, true, h.serveClearKillSwitch,
+		},
+		Route{
+			"drain",
+			"POST", "/api/v1/drain", false, true, h.serveDrain,
+		},
+		Route{
+			"resume",
+			"DELETE", "/api/v1/drain", false, true, h.serveResume,
 		},
 	}...)
 
@@ -395,6 +447,25 @@ func (h *Handler) Open() {
 	if h.Config.AuthEnabled && h.Config.SharedSecret == "" {
 		h.Logger.Info("Auth is enabled but shared-secret is blank. BearerAuthentication is disabled.")
 	}
+
+	if h.Config.AuditLogPath != "" {
+		l, err := OpenAuditLog(h.Config.AuditLogPath)
+		if err != nil {
+			h.Logger.Error("unable to open audit log", zap.Error(err), zap.String("path", h.Config.AuditLogPath))
+		} else {
+			h.auditMu.Lock()
+			h.auditLog = l
+			h.auditMu.Unlock()
+			h.Logger.Info("opened audit log", zap.String("path", h.Config.AuditLogPath))
+		}
+	}
+
+	if h.tagEnricher != nil {
+		h.tagEnricher.Logger = h.Logger
+		if err := h.tagEnricher.Load(); err != nil {
+			h.Logger.Error("unable to load write enrichment table", zap.Error(err), zap.String("path", h.Config.EnrichmentFile))
+		}
+	}
 }
 
 func (h *Handler) Close() {
@@ -407,6 +478,13 @@ func (h *Handler) Close() {
 		h.accessLog = nil
 		h.accessLogFilters = nil
 	}
+
+	h.auditMu.Lock()
+	if h.auditLog != nil {
+		h.auditLog.Close()
+		h.auditLog = nil
+	}
+	h.auditMu.Unlock()
 }
 
 // Statistics maintains statistics for the httpd service.
@@ -435,6 +513,8 @@ type Statistics struct {
 	PromReadRequests             int64
 	FluxQueryRequests            int64
 	FluxQueryRequestDuration     int64
+	RateLimitedRequests          int64
+	PointsOutsideWindow          int64
 }
 
 // Statistics returns statistics for periodic monitoring.
@@ -466,6 +546,8 @@ func (h *Handler) Statistics(tags map[string]string) []models.Statistic {
 			statPromReadRequest:              atomic.LoadInt64(&h.stats.PromReadRequests),
 			statFluxQueryRequests:            atomic.LoadInt64(&h.stats.FluxQueryRequests),
 			statFluxQueryRequestDuration:     atomic.LoadInt64(&h.stats.FluxQueryRequestDuration),
+			statRateLimitedRequests:          atomic.LoadInt64(&h.stats.RateLimitedRequests),
+			statPointsOutsideWindow:          atomic.LoadInt64(&h.stats.PointsOutsideWindow),
 		},
 	}}
 }
@@ -494,9 +576,19 @@ func (h *Handler) AddRoutes(routes ...Route) {
 			}
 		}
 
+		// Health checks answer immediately, so client deadlines are not applied
+		// to them and a malformed deadline header cannot fail them.
+		switch r.Name {
+		case "ping", "ping-head", "status", "status-head", "health", "health-options":
+		default:
+			handler = h.requestDeadline(handler)
+		}
 		handler = h.responseWriter(handler)
+		if h.Config.AuditLogPath != "" {
+			handler = h.audit(handler, r.Name)
+		}
 		if r.Gzipped {
-			handler = gzipFilter(handler)
+			handler = compressFilter(handler)
 		}
 
 		handler = h.SetHeadersHandler(handler)
@@ -632,9 +724,10 @@ func (h *Handler) serveQuery(w http.ResponseWriter, r *http.Request, user meta.U
 	// Parse query from query string.
 	q, err := p.ParseQuery()
 	if err != nil {
-		h.httpError(rw, "error parsing query: "+err.Error(), http.StatusBadRequest)
+		h.httpErrorCode(rw, "error parsing query: "+err.Error(), http.StatusBadRequest, ErrorCodeParse)
 		return
 	}
+	auditQuery(r, q, db)
 
 	// Check authorization.
 	var fineAuthorizer query.FineAuthorizer
@@ -656,6 +749,26 @@ func (h *Handler) serveQuery(w http.ResponseWriter, r *http.Request, user meta.U
 		fineAuthorizer = query.OpenAuthorizer
 	}
 
+	// Reject the query if any database it targets has queries disabled.
+	if database, reason, disabled := h.queryKillSwitch(q, db); disabled {
+		h.httpError(rw, fmt.Sprintf("queries to database %q are disabled: %s", database, reason), http.StatusForbidden)
+		return
+	} else if database, reason, disabled := h.queryWriteKillSwitch(q, db); disabled {
+		h.httpError(rw, fmt.Sprintf("writes to database %q are disabled: %s", database, reason), http.StatusForbidden)
+		return
+	}
+
+	// Statements that write or delete data are rejected while draining.
+	if len(queryWriteDatabases(q, db)) > 0 && h.rejectDraining(rw) {
+		return
+	}
+
+	for _, database := range queryDatabases(q, db) {
+		if h.rateLimited(rw, h.queryRateLimiter, database, "query", 1) {
+			return
+		}
+	}
+
 	// Parse chunk size. Use default if not provided or unparsable.
 	chunked := r.FormValue("chunked") == "true"
 	chunkSize := DefaultChunkSize
@@ -700,12 +813,14 @@ func (h *Handler) serveQuery(w http.ResponseWriter, r *http.Request, user meta.U
 
 			notify := notifier.CloseNotify()
 			go func() {
-				// Wait for either the request to finish
-				// or for the client to disconnect
+				// Wait for either the request to finish, for the client
+				// to disconnect or for the request deadline to pass.
 				select {
 				case <-done:
 				case <-notify:
 					close(closing)
+				case <-r.Context().Done():
+					close(closing)
 				}
 			}()
 			opts.AbortCh = done
@@ -908,6 +1023,10 @@ type DeleteBody struct {
 // of an "org" and "bucket" are mapped to v1 "database" and "retention
 // policies".
 func (h *Handler) serveDeleteV2(w http.ResponseWriter, r *http.Request, user meta.User) {
+	if h.rejectDraining(w) {
+		return
+	}
+
 	db, rp, err := bucket2dbrp(r.URL.Query().Get("bucket"))
 
 	if err != nil {
@@ -936,6 +1055,11 @@ func (h *Handler) serveDeleteV2(w http.ResponseWriter, r *http.Request, user met
 		}
 	}
 
+	if reason, disabled := h.killSwitches.WritesDisabled(db); disabled {
+		h.httpError(w, fmt.Sprintf("delete - writes to database %q are disabled: %s", db, reason), http.StatusForbidden)
+		return
+	}
+
 	var bs []byte
 	if r.ContentLength > 0 {
 		if h.Config.MaxBodySize > 0 && r.ContentLength > int64(h.Config.MaxBodySize) {
@@ -1600,8 +1724,10 @@ func (h *Handler) serveWriteV1(w http.ResponseWriter, r *http.Request, user meta
 	h.serveWrite(db, rp, precision, w, r, user)
 }
 
-// serveWrite receives incoming series data in line protocol format and writes
-// it to the database.
+// serveWrite receives incoming series data in line protocol or CSV format and
+// writes it to the database. The body may be compressed with gzip, zstd or
+// snappy. Snappy bodies must use the framing format rather than the block
+// format used by Prometheus remote write.
 func (h *Handler) serveWrite(database, retentionPolicy, precision string, w http.ResponseWriter, r *http.Request, user meta.User) {
 	atomic.AddInt64(&h.stats.WriteRequests, 1)
 	atomic.AddInt64(&h.stats.ActiveWriteRequests, 1)
@@ -1611,6 +1737,10 @@ func (h *Handler) serveWrite(database, retentionPolicy, precision string, w http
 	}(time.Now())
 	h.requestTracker.Add(r, user)
 
+	if h.rejectDraining(w) {
+		return
+	}
+
 	if database == "" {
 		h.httpError(w, "database is required", http.StatusBadRequest)
 		return
@@ -1621,6 +1751,11 @@ func (h *Handler) serveWrite(database, retentionPolicy, precision string, w http
 		return
 	}
 
+	metrics := newDBWriteMetrics(database)
+	defer func(start time.Time) {
+		metrics.duration.Observe(time.Since(start).Seconds())
+	}(time.Now())
+
 	if h.Config.AuthEnabled {
 		if user == nil {
 			h.httpError(w, fmt.Sprintf("user is required to write to database %q", database), http.StatusForbidden)
@@ -1633,20 +1768,71 @@ func (h *Handler) serveWrite(database, retentionPolicy, precision string, w http
 		}
 	}
 
-	body := r.Body
+	if reason, disabled := h.killSwitches.WritesDisabled(database); disabled {
+		h.httpError(w, fmt.Sprintf("writes to database %q are disabled: %s", database, reason), http.StatusForbidden)
+		return
+	}
+
+	// The body size is unknown until it has been read, so writes are
+	// admitted here and charged for their size below.
+	if h.rateLimited(w, h.writeRateLimiter, database, "write", 0) {
+		return
+	}
+
+	// CSV bodies are mapped onto points using the query parameters.
+	var mapping *csvMapping
+	if isCSVWrite(r) {
+		m, err := newCSVMapping(r.URL.Query())
+		if err != nil {
+			h.httpError(w, err.Error(), http.StatusBadRequest)
+			return
+		}
+		mapping = m
+	}
+
+	// Limit the size of the body as sent, so that a long stream that
+	// decompresses to little cannot be read without bound.
+	var body io.Reader = r.Body
 	if h.Config.MaxBodySize > 0 {
 		body = truncateReader(body, int64(h.Config.MaxBodySize))
 	}
 
-	// Handle gzip decoding of the body
-	if r.Header.Get("Content-Encoding") == "gzip" {
-		b, err := gzip.NewReader(r.Body)
+	// Handle decoding of compressed bodies.
+	encoding := r.Header.Get("Content-Encoding")
+	switch encoding {
+	case "gzip":
+		b, err := gzip.NewReader(body)
+		if errors.Is(err, errTruncated) {
+			h.httpError(w, http.StatusText(http.StatusRequestEntityTooLarge), http.StatusRequestEntityTooLarge)
+			return
+		} else if err != nil {
+			h.httpError(w, err.Error(), http.StatusBadRequest)
+			return
+		}
+		defer b.Close()
+		body = b
+	case "zstd":
+		opts := []zstd.DOption{zstd.WithDecoderConcurrency(1)}
+		if h.Config.MaxBodySize > 0 {
+			opts = append(opts, zstd.WithDecoderMaxMemory(uint64(h.Config.MaxBodySize)))
+		}
+		b, err := zstd.NewReader(body, opts...)
 		if err != nil {
 			h.httpError(w, err.Error(), http.StatusBadRequest)
 			return
 		}
 		defer b.Close()
 		body = b
+	case "snappy":
+		// Write bodies use the snappy framing format, which can be decoded as
+		// a stream, unlike the block format used by Prometheus remote write.
+		body = snappy.NewReader(body)
+	}
+
+	// Also limit the decompressed size so that a small compressed body
+	// cannot expand without bound.
+	if h.Config.MaxBodySize > 0 && (encoding == "gzip" || encoding == "zstd" || encoding == "snappy") {
+		body = truncateReader(body, int64(h.Config.MaxBodySize))
 	}
 
 	var bs []byte
@@ -1664,7 +1850,7 @@ func (h *Handler) serveWrite(database, retentionPolicy, precision string, w http
 
 	_, err := buf.ReadFrom(body)
 	if err != nil {
-		if err == errTruncated {
+		if errors.Is(err, errTruncated) {
 			h.httpError(w, http.StatusText(http.StatusRequestEntityTooLarge), http.StatusRequestEntityTooLarge)
 			return
 		}
@@ -1676,19 +1862,49 @@ func (h *Handler) serveWrite(database, retentionPolicy, precision string, w http
 		return
 	}
 	atomic.AddInt64(&h.stats.WriteRequestBytesReceived, int64(buf.Len()))
+	metrics.bytes.Add(float64(buf.Len()))
+	if h.writeRateLimiter != nil {
+		h.writeRateLimiter.Charge(database, float64(buf.Len()))
+	}
 
 	if h.Config.WriteTracing {
 		h.Logger.Info("Write body received by handler", zap.ByteString("body", buf.Bytes()))
 	}
 
-	points, parseError := models.ParsePointsWithPrecision(buf.Bytes(), time.Now().UTC(), precision)
+	var (
+		points     []models.Point
+		parseError error
+	)
+	if mapping != nil {
+		points, parseError = parseCSVPoints(buf.Bytes(), mapping, time.Now().UTC(), precision)
+	} else {
+		points, parseError = models.ParsePointsWithPrecision(buf.Bytes(), time.Now().UTC(), precision)
+	}
 	// Not points parsed correctly so return the error now
 	if parseError != nil && len(points) == 0 {
 		if parseError.Error() == "EOF" {
 			h.writeHeader(w, http.StatusOK)
 			return
 		}
-		h.httpError(w, parseError.Error(), http.StatusBadRequest)
+		metrics.rejected.Add(float64(parseFailures(parseError)))
+		h.httpErrorCode(w, parseError.Error(), http.StatusBadRequest, ErrorCodeParse)
+		return
+	}
+
+	if h.tagEnricher != nil {
+		h.tagEnricher.Enrich(points)
+	}
+
+	// Drop points outside the accepted time window.
+	points, outside := h.filterWriteWindow(points, metrics)
+	if outside > 0 && len(points) == 0 {
+		var perr tsdb.PartialWriteError
+		if parseError != nil {
+			perr.Reason = parseError.Error()
+			perr.Dropped = parseFailures(parseError)
+			metrics.rejected.Add(float64(perr.Dropped))
+		}
+		h.httpErrorCode(w, h.writeWindow.partialWriteError(perr, outside).Error(), http.StatusBadRequest, ErrorCodePartialWrite)
 		return
 	}
 
@@ -1704,39 +1920,73 @@ func (h *Handler) serveWrite(database, retentionPolicy, precision string, w http
 		}
 	}
 
+	// Skip the write if the client's deadline passed while the body was read.
+	if r.Context().Err() == context.DeadlineExceeded {
+		h.httpError(w, "request deadline exceeded", http.StatusGatewayTimeout)
+		return
+	}
+
 	// Write points.
 	if err := h.PointsWriter.WritePoints(database, retentionPolicy, consistency, user, points); influxdb.IsClientError(err) {
 		atomic.AddInt64(&h.stats.PointsWrittenFail, int64(len(points)))
+		metrics.rejected.Add(float64(len(points)))
 		h.httpError(w, err.Error(), http.StatusBadRequest)
 		return
 	} else if influxdb.IsAuthorizationError(err) {
 		atomic.AddInt64(&h.stats.PointsWrittenFail, int64(len(points)))
+		metrics.rejected.Add(float64(len(points)))
 		h.httpError(w, err.Error(), http.StatusForbidden)
 		return
 	} else if werr, ok := err.(tsdb.PartialWriteError); ok {
 		// Note - we don't always collect all the errors before returning from the call,
 		// so PointsWrittenOK might overestimate the number of successful points if multiple shards have errors
 		atomic.AddInt64(&h.stats.PointsWrittenOK, int64(len(points)-werr.Dropped))
+		metrics.points.Add(float64(len(points) - werr.Dropped))
 		atomic.AddInt64(&h.stats.PointsWrittenDropped, int64(werr.Dropped))
-		h.httpError(w, werr.Error(), http.StatusBadRequest)
+		metrics.rejected.Add(float64(werr.Dropped))
+		h.httpErrorCode(w, h.writeWindow.partialWriteError(werr, outside).Error(), http.StatusBadRequest, partialWriteCode(werr))
 		return
 	} else if err != nil {
 		atomic.AddInt64(&h.stats.PointsWrittenFail, int64(len(points)))
+		metrics.rejected.Add(float64(len(points)))
 		h.httpError(w, err.Error(), http.StatusInternalServerError)
 		return
 	} else if parseError != nil {
 		// We wrote some of the points
 		atomic.AddInt64(&h.stats.PointsWrittenOK, int64(len(points)))
+		metrics.points.Add(float64(len(points)))
+		metrics.rejected.Add(float64(parseFailures(parseError)))
 		// The other points failed to parse which means the client sent invalid line protocol.  We return a 400
 		// response code as well as the lines that failed to parse.
-		h.httpError(w, tsdb.PartialWriteError{Reason: parseError.Error()}.Error(), http.StatusBadRequest)
+		h.httpErrorCode(w, h.writeWindow.partialWriteError(tsdb.PartialWriteError{Reason: parseError.Error()}, outside).Error(), http.StatusBadRequest, ErrorCodePartialWrite)
+		return
+	} else if outside > 0 {
+		// The remaining points were written.
+		atomic.AddInt64(&h.stats.PointsWrittenOK, int64(len(points)))
+		metrics.points.Add(float64(len(points)))
+		h.httpErrorCode(w, h.writeWindow.partialWriteError(tsdb.PartialWriteError{}, outside).Error(), http.StatusBadRequest, ErrorCodePartialWrite)
 		return
 	}
 
 	atomic.AddInt64(&h.stats.PointsWrittenOK, int64(len(points)))
+	metrics.points.Add(float64(len(points)))
 	h.writeHeader(w, http.StatusNoContent)
 }
 
+// partialWriteCode returns the error code for a partial write.
+func partialWriteCode(err tsdb.PartialWriteError) ErrorCode {
+	if strings.HasPrefix(err.Reason, tsdb.ErrFieldTypeConflict.Error()) {
+		return ErrorCodeSchemaConflict
+	}
+	return ErrorCodePartialWrite
+}
+
+// parseFailures returns the number of lines or rows that failed to parse. The
+// parsers join the error of each failed line or row with a newline.
+func parseFailures(err error) int {
+	return strings.Count(err.Error(), "\n") + 1
+}
+
 // serveOptions returns an empty response to comply with OPTIONS pre-flight requests
 func (h *Handler) serveOptions(w http.ResponseWriter, r *http.Request) {
 	h.writeHeader(w, http.StatusNoContent)
@@ -1758,15 +2008,20 @@ func (h *Handler) servePing(w http.ResponseWriter, r *http.Request) {
 
 // serveHealth maps v2 health endpoint to ping endpoint
 func (h *Handler) serveHealth(w http.ResponseWriter, r *http.Request) {
+	message, status, code := "ready for queries and writes", "pass", http.StatusOK
+	if h.Draining() {
+		message, status, code = "draining, not accepting writes", "fail", http.StatusServiceUnavailable
+	}
+
 	resp := map[string]interface{}{
 		"name":    "influxdb",
-		"message": "ready for queries and writes",
-		"status":  "pass",
+		"message": message,
+		"status":  status,
 		"checks":  []string{},
 		"version": h.Version,
 	}
 	b, _ := json.Marshal(resp)
-	h.writeHeader(w, http.StatusOK)
+	h.writeHeader(w, code)
 	w.Header().Set("Content-Type", "application/json; charset=utf-8")
 	if _, err := w.Write(b); err != nil {
 		h.httpError(w, err.Error(), http.StatusInternalServerError)
@@ -1781,6 +2036,344 @@ func (h *Handler) serveStatus(w http.ResponseWriter, r *http.Request) {
 	h.writeHeader(w, http.StatusNoContent)
 }
 
+// authorizeAdmin ensures that the user is allowed to perform administrative
+// actions when authentication is enabled. A forbidden response is written and
+// false returned when the user is not an admin.
+func (h *Handler) authorizeAdmin(w http.ResponseWriter, r *http.Request, user meta.User) bool {
+	if !h.Config.AuthEnabled {
+		return true
+	}
+	if user == nil || !user.AuthorizeUnrestricted() {
+		var username string
+		if user != nil {
+			username = user.ID()
+		}
+		h.Logger.Info("Unauthorized request", zap.String("user", username), zap.String("path", r.URL.Path))
+		h.httpError(w, "error authorizing admin access", http.StatusForbidden)
+		return false
+	}
+	return true
+}
+
+// rateLimited writes a 429 response and returns true when the database has
+// exhausted its share of the rate limiter. Admitted requests are charged cost
+// tokens.
+func (h *Handler) rateLimited(w http.ResponseWriter, l *RateLimiter, database, kind string, cost float64) bool {
+	wait, ok := h.rateAllow(l, database, cost)
+	if !ok {
+		setRateLimitHeaders(w, wait, l.Limit())
+		h.httpError(w, fmt.Sprintf("%s rate limit exceeded for database %q", kind, database), http.StatusTooManyRequests)
+		return true
+	}
+	return false
+}
+
+// retryAfter formats a wait as the whole number of seconds of a Retry-After
+// header.
+func retryAfter(wait time.Duration) string {
+	return strconv.FormatInt(int64(math.Ceil(wait.Seconds())), 10)
+}
+
+// setRateLimitHeaders tells the client how long to wait before retrying a
+// rate limited request, and what the limit is.
+func setRateLimitHeaders(w http.ResponseWriter, wait time.Duration, limit float64) {
+	w.Header().Set("Retry-After", retryAfter(wait))
+	w.Header().Set("X-RateLimit-Limit", strconv.FormatFloat(limit, 'f', -1, 64))
+}
+
+// rateAllow admits a request for the database and charges it cost tokens. If
+// the request is rejected, the returned duration is how long to wait before
+// retrying. Databases that do not exist are not limited, so that requests
+// naming made-up databases cannot grow the limiter without bound.
+func (h *Handler) rateAllow(l *RateLimiter, database string, cost float64) (time.Duration, bool) {
+	if l == nil || h.MetaClient.Database(database) == nil {
+		return 0, true
+	}
+
+	wait, ok := l.Allow(database)
+	if !ok {
+		atomic.AddInt64(&h.stats.RateLimitedRequests, 1)
+		return wait, false
+	}
+
+	if cost > 0 {
+		l.Charge(database, cost)
+	}
+	return 0, true
+}
+
+// queryDatabases returns the databases targeted by the query, either through
+// the db parameter or fully qualified measurements, without duplicates.
+func queryDatabases(q *influxql.Query, db string) []string {
+	var dbs []string
+	seen := make(map[string]bool)
+	add := func(name string) {
+		if name != "" && !seen[name] {
+			seen[name] = true
+			dbs = append(dbs, name)
+		}
+	}
+
+	add(db)
+	influxql.WalkFunc(q, func(n influxql.Node) {
+		if m, ok := n.(*influxql.Measurement); ok {
+			add(m.Database)
+		}
+	})
+	return dbs
+}
+
+// queryKillSwitch returns the first database targeted by the query that
+// currently has queries disabled.
+func (h *Handler) queryKillSwitch(q *influxql.Query, db string) (database, reason string, disabled bool) {
+	for _, database := range queryDatabases(q, db) {
+		if reason, disabled := h.killSwitches.QueriesDisabled(database); disabled {
+			return database, reason, true
+		}
+	}
+	return "", "", false
+}
+
+// queryWriteDatabases returns the databases written to or modified by the
+// statements of the query, without duplicates. Statements that do not name a
+// database, such as DELETE, modify the database in the db parameter.
+func queryWriteDatabases(q *influxql.Query, db string) []string {
+	var dbs []string
+	seen := make(map[string]bool)
+	add := func(name string) {
+		if name == "" {
+			name = db
+		}
+		if name != "" && !seen[name] {
+			seen[name] = true
+			dbs = append(dbs, name)
+		}
+	}
+
+	for _, stmt := range q.Statements {
+		switch stmt := stmt.(type) {
+		case *influxql.SelectStatement:
+			if stmt.Target != nil {
+				add(stmt.Target.Measurement.Database)
+			}
+			continue
+		case *influxql.DropMeasurementStatement:
+			add("")
+			continue
+		}
+
+		privs, err := stmt.RequiredPrivileges()
+		if err != nil {
+			continue
+		}
+		for _, p := range privs {
+			if p.Privilege == influxql.WritePrivilege {
+				add(p.Name)
+			}
+		}
+	}
+	return dbs
+}
+
+// queryWriteKillSwitch returns the first database written to or modified by
+// the query that currently has writes disabled.
+func (h *Handler) queryWriteKillSwitch(q *influxql.Query, db string) (database, reason string, disabled bool) {
+	for _, database := range queryWriteDatabases(q, db) {
+		if reason, disabled := h.killSwitches.WritesDisabled(database); disabled {
+			return database, reason, true
+		}
+	}
+	return "", "", false
+}
+
+// fluxCheckError is returned by fluxDatabaseCheck so that serveFluxQuery can
+// respond with the same status as the equivalent InfluxQL or write request.
+type fluxCheckError struct {
+	status int
+	msg    string
+
+	// wait is how long the client should wait before retrying, and limit is
+	// the rate limit that was exceeded.
+	wait  time.Duration
+	limit float64
+}
+
+func (e *fluxCheckError) Error() string { return e.msg }
+
+// fluxDatabaseCheck returns an error if a Flux query may not read from, or
+// write to, the database because of a kill switch or the query rate limit.
+// Reads are charged against the rate limit once for each bucket they use, and
+// queries that write are recorded in the audit log.
+func (h *Handler) fluxDatabaseCheck(r *http.Request, db string, privilege influxql.Privilege) error {
+	if privilege == influxql.WritePrivilege {
+		auditFluxWrite(r, db)
+		if h.Draining() {
+			return &fluxCheckError{
+				status: http.StatusServiceUnavailable,
+				msg:    "server is draining, not accepting writes",
+				wait:   h.DrainTimeout(),
+			}
+		}
+		if reason, disabled := h.killSwitches.WritesDisabled(db); disabled {
+			return &fluxCheckError{
+				status: http.StatusForbidden,
+				msg:    fmt.Sprintf("writes to database %q are disabled: %s", db, reason),
+			}
+		}
+		return nil
+	}
+
+	if reason, disabled := h.killSwitches.QueriesDisabled(db); disabled {
+		return &fluxCheckError{
+			status: http.StatusForbidden,
+			msg:    fmt.Sprintf("queries to database %q are disabled: %s", db, reason),
+		}
+	}
+	if wait, ok := h.rateAllow(h.queryRateLimiter, db, 1); !ok {
+		return &fluxCheckError{
+			status: http.StatusTooManyRequests,
+			msg:    fmt.Sprintf("query rate limit exceeded for database %q", db),
+			wait:   wait,
+			limit:  h.queryRateLimiter.Limit(),
+		}
+	}
+	return nil
+}
+
+// serveKillSwitches lists the databases that have writes or queries disabled.
+func (h *Handler) serveKillSwitches(w http.ResponseWriter, r *http.Request, user meta.User) {
+	if !h.authorizeAdmin(w, r, user) {
+		return
+	}
+
+	b, err := json.Marshal(struct {
+		KillSwitches []KillSwitchState `json:"killSwitches"`
+	}{h.killSwitches.States()})
+	if err != nil {
+		h.httpError(w, fmt.Sprintf("kill switch - cannot marshal response: %s", err.Error()), http.StatusInternalServerError)
+		return
+	}
+	w.Header().Set("Content-Type", "application/json; charset=utf-8")
+	h.writeHeader(w, http.StatusOK)
+	w.Write(b)
+}
+
+// serveSetKillSwitch disables writes and/or queries for a database until the
+// kill switch is cleared. Rejected requests receive a 403 with the reason.
+// Kill switches are held in memory only and are cleared when the server
+// restarts.
+func (h *Handler) serveSetKillSwitch(w http.ResponseWriter, r *http.Request, user meta.User) {
+	if !h.authorizeAdmin(w, r, user) {
+		return
+	}
+
+	var state KillSwitchState
+	if err := json.NewDecoder(r.Body).Decode(&state); err != nil {
+		h.httpError(w, fmt.Sprintf("kill switch - cannot parse request body: %s", err.Error()), http.StatusBadRequest)
+		return
+	}
+
+	if e := auditEntryFrom(r); e != nil {
+		e.Database = state.Database
+	}
+
+	if state.Database == "" {
+		h.httpError(w, "kill switch - database is required", http.StatusBadRequest)
+		return
+	} else if di := h.MetaClient.Database(state.Database); di == nil {
+		h.httpError(w, fmt.Sprintf("kill switch - database not found: %q", state.Database), http.StatusNotFound)
+		return
+	}
+
+	h.killSwitches.Set(state)
+	h.Logger.Info("Kill switch updated",
+		logger.Database(state.Database),
+		zap.Bool("writes", state.Writes),
+		zap.Bool("queries", state.Queries),
+		zap.String("reason", state.Reason))
+	h.writeHeader(w, http.StatusNoContent)
+}
+
+// serveClearKillSwitch re-enables writes and queries for a database.
+func (h *Handler) serveClearKillSwitch(w http.ResponseWriter, r *http.Request, user meta.User) {
+	if !h.authorizeAdmin(w, r, user) {
+		return
+	}
+
+	db := r.URL.Query().Get("db")
+	if db == "" {
+		h.httpError(w, "kill switch - database is required", http.StatusBadRequest)
+		return
+	}
+
+	h.killSwitches.Clear(db)
+	h.Logger.Info("Kill switch cleared", logger.Database(db))
+	h.writeHeader(w, http.StatusNoContent)
+}
+
+// Drain stops the handler from accepting writes so that the server can be
+// shut down without losing acknowledged data. Writes include deletes, InfluxQL
+// statements that write or delete data, and Flux queries that use to().
+// Requests already in progress, and queries, are unaffected.
+func (h *Handler) Drain() {
+	if atomic.CompareAndSwapInt32(&h.draining, 0, 1) {
+		h.Logger.Info("Draining, new writes will be rejected")
+	}
+}
+
+// Resume accepts writes again after Drain.
+func (h *Handler) Resume() {
+	if atomic.CompareAndSwapInt32(&h.draining, 1, 0) {
+		h.Logger.Info("Drain cancelled, accepting writes")
+	}
+}
+
+// Draining returns true if Drain has been called.
+func (h *Handler) Draining() bool {
+	return atomic.LoadInt32(&h.draining) == 1
+}
+
+// DrainTimeout returns the time allowed for in-flight requests to finish on
+// shutdown. A zero drain-timeout uses the default.
+func (h *Handler) DrainTimeout() time.Duration {
+	if d := time.Duration(h.Config.DrainTimeout); d > 0 {
+		return d
+	}
+	return DefaultDrainTimeout
+}
+
+// rejectDraining writes a service unavailable response and returns true if
+// the handler is draining.
+func (h *Handler) rejectDraining(w http.ResponseWriter) bool {
+	if !h.Draining() {
+		return false
+	}
+	w.Header().Set("Retry-After", retryAfter(h.DrainTimeout()))
+	h.httpError(w, "server is draining, not accepting writes", http.StatusServiceUnavailable)
+	return true
+}
+
+// serveDrain puts the handler into drain mode ahead of a shutdown.
+func (h *Handler) serveDrain(w http.ResponseWriter, r *http.Request, user meta.User) {
+	if !h.authorizeAdmin(w, r, user) {
+		return
+	}
+
+	h.Drain()
+	h.writeHeader(w, http.StatusNoContent)
+}
+
+// serveResume takes the handler out of drain mode, for example after a
+// mistaken drain or a cancelled shutdown.
+func (h *Handler) serveResume(w http.ResponseWriter, r *http.Request, user meta.User) {
+	if !h.authorizeAdmin(w, r, user) {
+		return
+	}
+
+	h.Resume()
+	h.writeHeader(w, http.StatusNoContent)
+}
+
 // convertToEpoch converts result timestamps from time.Time to the specified epoch.
 func convertToEpoch(r *query.Result, epoch string) {
 	divisor := int64(1)
@@ -1819,6 +2412,10 @@ func (h *Handler) servePromWrite(w http.ResponseWriter, r *http.Request, user me
 	}(time.Now())
 	h.requestTracker.Add(r, user)
 
+	if h.rejectDraining(w) {
+		return
+	}
+
 	database := r.URL.Query().Get("db")
 	if database == "" {
 		h.httpError(w, "database is required", http.StatusBadRequest)
@@ -1830,6 +2427,11 @@ func (h *Handler) servePromWrite(w http.ResponseWriter, r *http.Request, user me
 		return
 	}
 
+	metrics := newDBWriteMetrics(database)
+	defer func(start time.Time) {
+		metrics.duration.Observe(time.Since(start).Seconds())
+	}(time.Now())
+
 	if h.Config.AuthEnabled {
 		if user == nil {
 			h.httpError(w, fmt.Sprintf("user is required to write to database %q", database), http.StatusForbidden)
@@ -1842,6 +2444,17 @@ func (h *Handler) servePromWrite(w http.ResponseWriter, r *http.Request, user me
 		}
 	}
 
+	if reason, disabled := h.killSwitches.WritesDisabled(database); disabled {
+		h.httpError(w, fmt.Sprintf("writes to database %q are disabled: %s", database, reason), http.StatusForbidden)
+		return
+	}
+
+	// The body size is unknown until it has been read, so writes are
+	// admitted here and charged for their size below.
+	if h.rateLimited(w, h.writeRateLimiter, database, "write", 0) {
+		return
+	}
+
 	body := r.Body
 	if h.Config.MaxBodySize > 0 {
 		body = truncateReader(body, int64(h.Config.MaxBodySize))
@@ -1874,6 +2487,10 @@ func (h *Handler) servePromWrite(w http.ResponseWriter, r *http.Request, user me
 		return
 	}
 	atomic.AddInt64(&h.stats.WriteRequestBytesReceived, int64(buf.Len()))
+	metrics.bytes.Add(float64(buf.Len()))
+	if h.writeRateLimiter != nil {
+		h.writeRateLimiter.Charge(database, float64(buf.Len()))
+	}
 
 	if h.Config.WriteTracing {
 		h.Logger.Info("Prom write body received by handler", zap.ByteString("body", buf.Bytes()))
@@ -1905,6 +2522,17 @@ func (h *Handler) servePromWrite(w http.ResponseWriter, r *http.Request, user me
 		}
 	}
 
+	if h.tagEnricher != nil {
+		h.tagEnricher.Enrich(points)
+	}
+
+	// Drop points outside the accepted time window.
+	points, outside := h.filterWriteWindow(points, metrics)
+	if outside > 0 && len(points) == 0 {
+		h.httpErrorCode(w, h.writeWindow.partialWriteError(tsdb.PartialWriteError{}, outside).Error(), http.StatusBadRequest, ErrorCodePartialWrite)
+		return
+	}
+
 	// Determine required consistency level.
 	level := r.URL.Query().Get("consistency")
 	consistency := models.ConsistencyLevelOne
@@ -1916,27 +2544,44 @@ func (h *Handler) servePromWrite(w http.ResponseWriter, r *http.Request, user me
 		}
 	}
 
+	// Skip the write if the client's deadline passed while the body was read.
+	if r.Context().Err() == context.DeadlineExceeded {
+		h.httpError(w, "request deadline exceeded", http.StatusGatewayTimeout)
+		return
+	}
+
 	// Write points.
 	if err := h.PointsWriter.WritePoints(database, r.URL.Query().Get("rp"), consistency, user, points); influxdb.IsClientError(err) {
 		atomic.AddInt64(&h.stats.PointsWrittenFail, int64(len(points)))
+		metrics.rejected.Add(float64(len(points)))
 		h.httpError(w, err.Error(), http.StatusBadRequest)
 		return
 	} else if influxdb.IsAuthorizationError(err) {
 		atomic.AddInt64(&h.stats.PointsWrittenFail, int64(len(points)))
+		metrics.rejected.Add(float64(len(points)))
 		h.httpError(w, err.Error(), http.StatusForbidden)
 		return
 	} else if werr, ok := err.(tsdb.PartialWriteError); ok {
 		atomic.AddInt64(&h.stats.PointsWrittenOK, int64(len(points)-werr.Dropped))
+		metrics.points.Add(float64(len(points) - werr.Dropped))
 		atomic.AddInt64(&h.stats.PointsWrittenDropped, int64(werr.Dropped))
-		h.httpError(w, werr.Error(), http.StatusBadRequest)
+		metrics.rejected.Add(float64(werr.Dropped))
+		h.httpErrorCode(w, h.writeWindow.partialWriteError(werr, outside).Error(), http.StatusBadRequest, partialWriteCode(werr))
 		return
 	} else if err != nil {
 		atomic.AddInt64(&h.stats.PointsWrittenFail, int64(len(points)))
+		metrics.rejected.Add(float64(len(points)))
 		h.httpError(w, err.Error(), http.StatusInternalServerError)
 		return
+	} else if outside > 0 {
+		atomic.AddInt64(&h.stats.PointsWrittenOK, int64(len(points)))
+		metrics.points.Add(float64(len(points)))
+		h.httpErrorCode(w, h.writeWindow.partialWriteError(tsdb.PartialWriteError{}, outside).Error(), http.StatusBadRequest, ErrorCodePartialWrite)
+		return
 	}
 
 	atomic.AddInt64(&h.stats.PointsWrittenOK, int64(len(points)))
+	metrics.points.Add(float64(len(points)))
 	h.writeHeader(w, http.StatusNoContent)
 }
 
@@ -1978,6 +2623,15 @@ func (h *Handler) servePromRead(w http.ResponseWriter, r *http.Request, user met
 		}
 	}
 
+	if reason, disabled := h.killSwitches.QueriesDisabled(db); disabled {
+		h.httpError(w, fmt.Sprintf("queries to database %q are disabled: %s", db, reason), http.StatusForbidden)
+		return
+	}
+
+	if h.rateLimited(w, h.queryRateLimiter, db, "query", 1) {
+		return
+	}
+
 	readRequest, err := prometheus.ReadRequestToInfluxStorageRequest(&req, db, rp)
 	if err != nil {
 		h.httpError(w, err.Error(), http.StatusBadRequest)
@@ -2104,6 +2758,12 @@ func (h *Handler) serveFluxQuery(w http.ResponseWriter, r *http.Request, user me
 		ctx = meta.NewContextWithUser(ctx, user)
 	}
 
+	// Fail the query if it reads from or writes to a database that has been
+	// disabled with a kill switch, or reads past the query rate limit.
+	ctx = influxdb2.NewContextWithDatabaseCheck(ctx, func(db string, privilege influxql.Privilege) error {
+		return h.fluxDatabaseCheck(r, db, privilege)
+	})
+
 	pr := req.ProxyRequest()
 
 	// Logging
@@ -2119,7 +2779,7 @@ func (h *Handler) serveFluxQuery(w http.ResponseWriter, r *http.Request, user me
 
 	q, err := h.Controller.Query(ctx, pr.Compiler)
 	if err != nil {
-		h.httpError(w, err.Error(), http.StatusInternalServerError)
+		h.fluxQueryError(w, err)
 		return
 	}
 	defer func() {
@@ -2157,12 +2817,34 @@ func (h *Handler) serveFluxQuery(w http.ResponseWriter, r *http.Request, user me
 		if err != nil {
 			if n == 0 {
 				// If the encoder did not write anything, we can write an error header.
-				h.httpError(w, err.Error(), http.StatusInternalServerError)
+				h.fluxQueryError(w, err)
 			}
 		}
 	}
 }
 
+// fluxQueryError writes the error of a failed Flux query. Queries rejected by
+// fluxDatabaseCheck get the status of the check, and rate limited or draining
+// queries get the same headers as the equivalent InfluxQL or write requests.
+// Invalid queries, such as those that fail to compile, are client errors.
+func (h *Handler) fluxQueryError(w http.ResponseWriter, err error) {
+	var cerr *fluxCheckError
+	switch {
+	case errors.As(err, &cerr):
+		switch cerr.status {
+		case http.StatusTooManyRequests:
+			setRateLimitHeaders(w, cerr.wait, cerr.limit)
+		case http.StatusServiceUnavailable:
+			w.Header().Set("Retry-After", retryAfter(cerr.wait))
+		}
+		h.httpError(w, err.Error(), cerr.status)
+	case flux.ErrorCode(err) == codes.Invalid:
+		h.httpError(w, err.Error(), http.StatusBadRequest)
+	default:
+		h.httpError(w, err.Error(), http.StatusInternalServerError)
+	}
+}
+
 func (h *Handler) serveFluxQueryDisabled(w http.ResponseWriter, r *http.Request, user meta.User) {
 	h.Logger.Warn("Received flux query but flux-enabled=false in [http] section of InfluxDB config")
 	h.httpError(w, "Flux query service disabled. Verify flux-enabled=true in the [http] section of the InfluxDB config.", http.StatusForbidden)
@@ -2524,6 +3206,17 @@ func parseCryptoDiagnostics(d *diagnostics.Diagnostics) (map[string]interface{},
 
 // httpError writes an error to the client in a standard format.
 func (h *Handler) httpError(w http.ResponseWriter, errmsg string, code int) {
+	h.httpErrorCode(w, errmsg, code, errorCodeForStatus(code))
+}
+
+// httpErrorCode writes an error with a specific error code to the client.
+func (h *Handler) httpErrorCode(w http.ResponseWriter, errmsg string, code int, errCode ErrorCode) {
+	if code/100 == 2 {
+		errCode = ""
+	} else {
+		w.Header().Set("X-InfluxDB-Error-Code", string(errCode))
+	}
+
 	if code == http.StatusUnauthorized {
 		// If an unauthorized header will be sent back, add a WWW-Authenticate header
 		// as an authorization challenge.
@@ -2533,7 +3226,7 @@ func (h *Handler) httpError(w http.ResponseWriter, errmsg string, code int) {
 		w.Header().Set("X-InfluxDB-Error", errmsg[:int(sz)])
 	}
 
-	response := Response{Err: errors.New(errmsg)}
+	response := Response{Err: errors.New(errmsg), Code: errCode}
 	if rw, ok := w.(ResponseWriter); ok {
 		h.writeHeader(w, code)
 		rw.WriteResponse(response)
@@ -2715,6 +3408,9 @@ func authenticate(inner func(http.ResponseWriter, *http.Request, meta.User), h *
 			}
 
 		}
+		if e := auditEntryFrom(r); e != nil && user != nil {
+			e.User = user.ID()
+		}
 		inner(w, r, user)
 	})
 }
@@ -2740,9 +3436,11 @@ func cors(inner http.Handler) http.Handler {
 				`Authorization`,
 				`Content-Length`,
 				`Content-Type`,
+				`Grpc-Timeout`,
 				`User-Agent`,
 				`X-CSRF-Token`,
 				`X-HTTP-Method-Override`,
+				`X-Request-Deadline`,
 			}, ", "))
 
 			w.Header().Set(`Access-Control-Expose-Headers`, strings.Join([]string{
@@ -2884,6 +3582,7 @@ type Store interface {
 type Response struct {
 	Results []*query.Result
 	Err     error
+	Code    ErrorCode
 }
 
 // MarshalJSON encodes a Response struct into JSON.
@@ -2892,6 +3591,7 @@ func (r Response) MarshalJSON() ([]byte, error) {
 	var o struct {
 		Results []*query.Result `json:"results,omitempty"`
 		Err     string          `json:"error,omitempty"`
+		Code    ErrorCode       `json:"code,omitempty"`
 	}
 
 	// Copy fields to output struct.
@@ -2899,6 +3599,7 @@ func (r Response) MarshalJSON() ([]byte, error) {
 	if r.Err != nil {
 		o.Err = r.Err.Error()
 	}
+	o.Code = r.Code
 
 	return json.Marshal(&o)
 }
@@ -2908,6 +3609,7 @@ func (r *Response) UnmarshalJSON(b []byte) error {
 	var o struct {
 		Results []*query.Result `json:"results,omitempty"`
 		Err     string          `json:"error,omitempty"`
+		Code    ErrorCode       `json:"code,omitempty"`
 	}
 
 	err := json.Unmarshal(b, &o)
@@ -2915,6 +3617,7 @@ func (r *Response) UnmarshalJSON(b []byte) error {
 		return err
 	}
 	r.Results = o.Results
+	r.Code = o.Code
 	if o.Err != "" {
 		r.Err = errors.New(o.Err)
 	}
@@ -2976,6 +3679,12 @@ func (t *Throttler) Handler(h http.Handler) http.Handler {
 			timerCh = timer.C
 		}
 
+		// Stop waiting once the request's context is done.
+		var doneCh <-chan struct{}
+		if r != nil {
+			doneCh = r.Context().Done()
+		}
+
 		// Wait for a spot in the queue.
 		if cap(t.enqueued) > cap(t.current) {
 			select {
@@ -2983,6 +3692,7 @@ func (t *Throttler) Handler(h http.Handler) http.Handler {
 				defer func() { <-t.enqueued }()
 			default:
 				t.Logger.Warn("request throttled, queue full", zap.Duration("d", timeout))
+				w.Header().Set("X-InfluxDB-Error-Code", string(ErrorCodeOverloaded))
 				http.Error(w, "request throttled, queue full", http.StatusServiceUnavailable)
 				return
 			}
@@ -2998,8 +3708,17 @@ func (t *Throttler) Handler(h http.Handler) http.Handler {
 			case t.current <- struct{}{}:
 			case <-timerCh:
 				t.Logger.Warn("request throttled, exceeds timeout", zap.Duration("d", timeout))
+				w.Header().Set("X-InfluxDB-Error-Code", string(ErrorCodeOverloaded))
 				http.Error(w, "request throttled, exceeds timeout", http.StatusServiceUnavailable)
 				return
+			case <-doneCh:
+				// A client that disconnected is not waiting for a response.
+				if r.Context().Err() == context.DeadlineExceeded {
+					t.Logger.Warn("request throttled, deadline exceeded")
+					w.Header().Set("X-InfluxDB-Error-Code", string(ErrorCodeDeadlineExceeded))
+					http.Error(w, "request throttled, deadline exceeded", http.StatusGatewayTimeout)
+				}
+				return
 			}
 		}
 		defer func() { <-t.current }()
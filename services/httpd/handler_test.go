@@ -2,6 +2,7 @@ package httpd_test
 
 import (
 	"bytes"
+	"compress/gzip"
 	"context"
 	"encoding/json"
 	"errors"
@@ -14,6 +15,7 @@ import (
 	"net/http/httptest"
 	"net/url"
 	"os"
+	"path/filepath"
 	"reflect"
 	"sort"
 	"strings"
@@ -25,9 +27,11 @@ import (
 	"github.com/golang/snappy"
 	"github.com/google/go-cmp/cmp"
 	"github.com/influxdata/flux"
+	"github.com/influxdata/flux/codes"
 	"github.com/influxdata/flux/lang"
 	"github.com/influxdata/flux/mock"
 	"github.com/influxdata/influxdb/flux/client"
+	influxdb2 "github.com/influxdata/influxdb/flux/stdlib/influxdata/influxdb"
 	"github.com/influxdata/influxdb/internal"
 	"github.com/influxdata/influxdb/logger"
 	"github.com/influxdata/influxdb/models"
@@ -39,8 +43,10 @@ import (
 	"github.com/influxdata/influxdb/services/meta"
 	"github.com/influxdata/influxdb/storage/reads"
 	"github.com/influxdata/influxdb/storage/reads/datatypes"
+	"github.com/influxdata/influxdb/toml"
 	"github.com/influxdata/influxdb/tsdb"
 	"github.com/influxdata/influxql"
+	"github.com/klauspost/compress/zstd"
 	"github.com/prometheus/prometheus/prompb"
 )
 
@@ -194,7 +200,7 @@ func TestHandler_Query_Auth(t *testing.T) {
 	h.ServeHTTP(w, req)
 	if w.Code != http.StatusUnauthorized {
 		t.Fatalf("unexpected status: %d: %s", w.Code, w.Body.String())
-	} else if body := strings.TrimSpace(w.Body.String()); body != `{"error":"signature is invalid"}` {
+	} else if body := strings.TrimSpace(w.Body.String()); body != `{"error":"signature is invalid","code":"unauthorized"}` {
 		t.Fatalf("unexpected body: %s", body)
 	}
 
@@ -206,7 +212,7 @@ func TestHandler_Query_Auth(t *testing.T) {
 	h.ServeHTTP(w, req)
 	if w.Code != http.StatusUnauthorized {
 		t.Fatalf("unexpected status: %d: %s", w.Code, w.Body.String())
-	} else if body := strings.TrimSpace(w.Body.String()); body != `{"error":"user not found"}` {
+	} else if body := strings.TrimSpace(w.Body.String()); body != `{"error":"user not found","code":"unauthorized"}` {
 		t.Fatalf("unexpected body: %s", body)
 	}
 
@@ -234,7 +240,7 @@ func TestHandler_Query_Auth(t *testing.T) {
 	h.ServeHTTP(w, req)
 	if w.Code != http.StatusUnauthorized {
 		t.Fatalf("unexpected status: %d: %s", w.Code, w.Body.String())
-	} else if body := strings.TrimSpace(w.Body.String()); body != `{"error":"token expiration required"}` {
+	} else if body := strings.TrimSpace(w.Body.String()); body != `{"error":"token expiration required","code":"unauthorized"}` {
 		t.Fatalf("unexpected body: %s", body)
 	}
 
@@ -251,7 +257,7 @@ func TestHandler_Query_Auth(t *testing.T) {
 	h.ServeHTTP(w, req)
 	if w.Code != http.StatusUnauthorized {
 		t.Fatalf("unexpected status: %d: %s", w.Code, w.Body.String())
-	} else if body := strings.TrimSpace(w.Body.String()); body != `{"error":"bearer auth disabled"}` {
+	} else if body := strings.TrimSpace(w.Body.String()); body != `{"error":"bearer auth disabled","code":"unauthorized"}` {
 		t.Fatalf("unexpected body: %s", body)
 	}
 	h.Config.SharedSecret = origSecret
@@ -378,6 +384,71 @@ func TestHandler_Query_Chunked(t *testing.T) {
 	}
 }
 
+// Ensure the handler compresses query responses using the accepted encoding.
+func TestHandler_Query_Compressed(t *testing.T) {
+	h := NewHandler(false)
+	h.StatementExecutor.ExecuteStatementFn = func(stmt influxql.Statement, ctx *query.ExecutionContext) error {
+		return ctx.Send(&query.Result{StatementID: 1, Series: models.Rows([]*models.Row{{Name: "series0"}})})
+	}
+
+	for _, tt := range []struct {
+		acceptEncoding string
+		encoding       string
+	}{
+		{"", ""},
+		{"identity", ""},
+		{"gzip", "gzip"},
+		{"gzip, deflate", "gzip"},
+		{"zstd", "zstd"},
+		{"gzip, zstd", "zstd"},
+		{"gzip, zstd;q=0", "gzip"},
+		{"gzip;q=1.0, zstd;q=0.5", "gzip"},
+		{"zstd;q=0.2, gzip;q=0.8, identity;q=0.9", ""},
+		{"*", "zstd"},
+		{"*;q=0.5, zstd;q=0.1", "gzip"},
+		{"*;q=0", ""},
+		{"identity;q=0, gzip;q=0.1", "gzip"},
+		{"identity;q=0", ""},
+		{"gzip;q=bad", ""},
+	} {
+		t.Run(tt.acceptEncoding, func(t *testing.T) {
+			req := MustNewJSONRequest("GET", "/query?db=foo&q=SELECT+*+FROM+bar", nil)
+			req.Header.Set("Accept-Encoding", tt.acceptEncoding)
+			w := httptest.NewRecorder()
+			h.ServeHTTP(w, req)
+			if w.Code != http.StatusOK {
+				t.Fatalf("unexpected status: %d", w.Code)
+			} else if got := w.Header().Get("Content-Encoding"); got != tt.encoding {
+				t.Fatalf("unexpected content encoding: exp %q, got %q", tt.encoding, got)
+			}
+
+			var body io.Reader = w.Body
+			switch tt.encoding {
+			case "gzip":
+				gr, err := gzip.NewReader(body)
+				if err != nil {
+					t.Fatal(err)
+				}
+				body = gr
+			case "zstd":
+				zr, err := zstd.NewReader(body)
+				if err != nil {
+					t.Fatal(err)
+				}
+				defer zr.Close()
+				body = zr
+			}
+
+			b, err := io.ReadAll(body)
+			if err != nil {
+				t.Fatal(err)
+			} else if got, exp := strings.TrimSpace(string(b)), `{"results":[{"statement_id":1,"series":[{"name":"series0"}]}]}`; got != exp {
+				t.Fatalf("unexpected body: %s", got)
+			}
+		})
+	}
+}
+
 // Ensure the handler can accept an async query.
 func TestHandler_Query_Async(t *testing.T) {
 	done := make(chan struct{})
@@ -420,7 +491,7 @@ func TestHandler_Query_ErrQueryRequired(t *testing.T) {
 	h.ServeHTTP(w, MustNewJSONRequest("GET", "/query", nil))
 	if w.Code != http.StatusBadRequest {
 		t.Fatalf("unexpected status: %d", w.Code)
-	} else if body := strings.TrimSpace(w.Body.String()); body != `{"error":"missing required parameter \"q\""}` {
+	} else if body := strings.TrimSpace(w.Body.String()); body != `{"error":"missing required parameter \"q\"","code":"invalid"}` {
 		t.Fatalf("unexpected body: %s", body)
 	}
 }
@@ -432,11 +503,54 @@ func TestHandler_Query_ErrInvalidQuery(t *testing.T) {
 	h.ServeHTTP(w, MustNewJSONRequest("GET", "/query?q=SELECT", nil))
 	if w.Code != http.StatusBadRequest {
 		t.Fatalf("unexpected status: %d", w.Code)
-	} else if body := strings.TrimSpace(w.Body.String()); body != `{"error":"error parsing query: found EOF, expected identifier, string, number, bool at line 1, char 8"}` {
+	} else if body := strings.TrimSpace(w.Body.String()); body != `{"error":"error parsing query: found EOF, expected identifier, string, number, bool at line 1, char 8","code":"parse_error"}` {
 		t.Fatalf("unexpected body: %s", body)
 	}
 }
 
+// Ensure error responses carry a machine-readable error code.
+func TestHandler_ErrorCode(t *testing.T) {
+	h := NewHandlerWithConfig(NewHandlerConfig(WithFlux(), WithNoLog()))
+	h.MetaClient.DatabaseFn = func(name string) *meta.DatabaseInfo {
+		return &meta.DatabaseInfo{}
+	}
+	h.PointsWriter.WritePointsFn = func(database, _ string, _ models.ConsistencyLevel, _ meta.User, points []models.Point) error {
+		if database == "foo" {
+			return tsdb.PartialWriteError{Reason: `field type conflict: input field "value" on measurement "cpu" is type string, already exists as type float`, Dropped: 1}
+		}
+		return tsdb.PartialWriteError{Reason: "max-series-per-database limit exceeded: (1)", Dropped: 1}
+	}
+	h.Controller.QueryFn = func(ctx context.Context, compiler flux.Compiler) (flux.Query, error) {
+		return nil, &flux.Error{Code: codes.Invalid, Msg: "error @1:1-1:3: undefined identifier v1"}
+	}
+
+	fluxReq := MustNewJSONRequest("POST", "/api/v2/query", strings.NewReader(`{"query":"v1.databases()"}`))
+	fluxReq.Header.Set("Content-Type", "application/json")
+	for _, tt := range []struct {
+		req  *http.Request
+		code string
+	}{
+		{req: MustNewJSONRequest("GET", "/query?q=SELECT", nil), code: "parse_error"},
+		{req: MustNewRequest("POST", "/write?db=foo", strings.NewReader("cpu value=")), code: "parse_error"},
+		{req: MustNewRequest("POST", "/write?db=foo", strings.NewReader("cpu value=1")), code: "schema_conflict"},
+		{req: MustNewRequest("POST", "/write?db=bar", strings.NewReader("cpu value=1")), code: "partial_write"},
+		{req: MustNewRequest("POST", "/write", strings.NewReader("cpu value=1")), code: "invalid"},
+		{req: fluxReq, code: "invalid"},
+	} {
+		w := httptest.NewRecorder()
+		h.ServeHTTP(w, tt.req)
+
+		var resp httpd.Response
+		if got := w.Header().Get("X-InfluxDB-Error-Code"); got != tt.code {
+			t.Fatalf("%s: unexpected error code header: %q", tt.req.URL, got)
+		} else if err := json.Unmarshal(w.Body.Bytes(), &resp); err != nil {
+			t.Fatalf("%s: %s", tt.req.URL, err)
+		} else if string(resp.Code) != tt.code {
+			t.Fatalf("%s: unexpected error code: %q", tt.req.URL, resp.Code)
+		}
+	}
+}
+
 // Ensure the handler returns an appropriate 401 or 403 status when authentication or authorization fails.
 func TestHandler_Query_ErrAuthorize(t *testing.T) {
 	h := NewHandler(true)
@@ -596,6 +710,67 @@ func TestHandler_Query_CloseNotify(t *testing.T) {
 	}
 }
 
+// Ensure the handler interrupts a query once the client's deadline passes.
+func TestHandler_Query_Deadline(t *testing.T) {
+	interrupted := make(chan struct{})
+	h := NewHandler(false)
+	h.StatementExecutor.ExecuteStatementFn = func(stmt influxql.Statement, ctx *query.ExecutionContext) error {
+		select {
+		case <-ctx.Done():
+			close(interrupted)
+		case <-time.After(5 * time.Second):
+		}
+		return nil
+	}
+
+	req := MustNewJSONRequest("GET", "/query?db=foo&q=SELECT+*+FROM+bar", nil)
+	req.Header.Set("Grpc-Timeout", "50m")
+	h.ServeHTTP(httptest.NewRecorder(), req)
+
+	select {
+	case <-interrupted:
+	default:
+		t.Fatal("query was not interrupted")
+	}
+}
+
+// Ensure the handler rejects requests whose deadline has already passed.
+func TestHandler_Deadline_Exceeded(t *testing.T) {
+	h := NewHandler(false)
+	h.PointsWriter.WritePointsFn = func(database, retentionPolicy string, consistencyLevel models.ConsistencyLevel, user meta.User, points []models.Point) error {
+		t.Fatal("points written after the deadline")
+		return nil
+	}
+	h.MetaClient.DatabaseFn = func(name string) *meta.DatabaseInfo {
+		return &meta.DatabaseInfo{}
+	}
+
+	req := MustNewRequest("POST", "/write?db=foo", strings.NewReader("cpu value=1"))
+	req.Header.Set("X-Request-Deadline", time.Now().Add(-time.Second).Format(time.RFC3339Nano))
+	w := httptest.NewRecorder()
+	h.ServeHTTP(w, req)
+	if w.Code != http.StatusGatewayTimeout {
+		t.Fatalf("unexpected status: %d", w.Code)
+	}
+
+	req = MustNewRequest("POST", "/write?db=foo", strings.NewReader("cpu value=1"))
+	req.Header.Set("X-Request-Deadline", "tomorrow")
+	w = httptest.NewRecorder()
+	h.ServeHTTP(w, req)
+	if w.Code != http.StatusBadRequest {
+		t.Fatalf("unexpected status: %d", w.Code)
+	}
+
+	// Health checks ignore deadline headers.
+	req = MustNewRequest("GET", "/ping", nil)
+	req.Header.Set("Grpc-Timeout", "soon")
+	w = httptest.NewRecorder()
+	h.ServeHTTP(w, req)
+	if w.Code != http.StatusNoContent {
+		t.Fatalf("unexpected status: %d", w.Code)
+	}
+}
+
 // Ensure the handler returns an appropriate 401 status when authentication
 // fails on ping endpoints.
 func TestHandler_Ping_ErrAuthorize(t *testing.T) {
@@ -963,7 +1138,7 @@ func TestHandler_PromWrite_Error(t *testing.T) {
 		t.Fatalf("unexpected status: %d", w.Code)
 	}
 
-	if got, exp := strings.TrimSpace(w.Body.String()), `{"error":"max key length exceeded: 65572 \u003e 65535"}`; got != exp {
+	if got, exp := strings.TrimSpace(w.Body.String()), `{"error":"max key length exceeded: 65572 \u003e 65535","code":"invalid"}`; got != exp {
 		t.Fatalf("got error %q, expected %q", got, exp)
 	}
 
@@ -1186,7 +1361,7 @@ func TestHandler_Flux_DisabledByDefault(t *testing.T) {
 		t.Fatalf("unexpected status: %d", got)
 	}
 
-	exp := `{"error":"Flux query service disabled. Verify flux-enabled=true in the [http] section of the InfluxDB config."}` + "\n"
+	exp := `{"error":"Flux query service disabled. Verify flux-enabled=true in the [http] section of the InfluxDB config.","code":"forbidden"}` + "\n"
 	if got := w.Body.String(); got != exp {
 		t.Fatalf("unexpected body -got/+exp\n%s", cmp.Diff(got, exp))
 	}
@@ -1348,7 +1523,7 @@ func TestHandler_Flux(t *testing.T) {
 				return MustNewRequest("POST", "/api/v2/query", nil)
 			},
 			expCode: http.StatusBadRequest,
-			expBody: "{\"error\":\"mime: no media type\"}\n",
+			expBody: "{\"error\":\"mime: no media type\",\"code\":\"invalid\"}\n",
 		},
 		{
 			name: "200 OK",
@@ -1519,6 +1694,118 @@ func TestHandler_Version(t *testing.T) {
 	}
 }
 
+// Ensure the metrics endpoint can be filtered and served as OpenMetrics.
+func TestHandler_Metrics(t *testing.T) {
+	h := NewHandler(false)
+
+	get := func(url, accept string) *httptest.ResponseRecorder {
+		req := MustNewRequest("GET", url, nil)
+		if accept != "" {
+			req.Header.Set("Accept", accept)
+		}
+		w := httptest.NewRecorder()
+		h.ServeHTTP(w, req)
+		if w.Code != http.StatusOK {
+			t.Fatalf("unexpected status for %s: %d", url, w.Code)
+		}
+		return w
+	}
+
+	w := get("/metrics?name=go_goroutines", "")
+	if body := w.Body.String(); !strings.Contains(body, "go_goroutines ") {
+		t.Fatalf("expected go_goroutines in body: %s", body)
+	} else if strings.Contains(body, "go_threads") {
+		t.Fatalf("unexpected go_threads in body: %s", body)
+	}
+
+	w = get("/metrics?prefix=go_memstats_&name=go_threads", "")
+	if body := w.Body.String(); !strings.Contains(body, "go_memstats_alloc_bytes ") || !strings.Contains(body, "go_threads ") {
+		t.Fatalf("expected go_memstats_ and go_threads metrics in body: %s", body)
+	} else if strings.Contains(body, "go_goroutines") {
+		t.Fatalf("unexpected go_goroutines in body: %s", body)
+	}
+
+	w = get("/metrics?name=go_goroutines", "application/openmetrics-text; version=0.0.1")
+	if ct := w.Header().Get("Content-Type"); !strings.HasPrefix(ct, "application/openmetrics-text") {
+		t.Fatalf("unexpected content type: %s", ct)
+	} else if body := w.Body.String(); !strings.HasSuffix(body, "# EOF\n") {
+		t.Fatalf("expected OpenMetrics EOF marker: %s", body)
+	}
+}
+
+// Ensure per-database write metrics are exposed on /metrics.
+func TestHandler_Metrics_Write(t *testing.T) {
+	h := NewHandler(false)
+	h.MetaClient.DatabaseFn = func(name string) *meta.DatabaseInfo {
+		return &meta.DatabaseInfo{Name: name}
+	}
+	h.PointsWriter.WritePointsFn = func(_, _ string, _ models.ConsistencyLevel, _ meta.User, points []models.Point) error {
+		return tsdb.PartialWriteError{Reason: "field type conflict", Dropped: 1}
+	}
+
+	body := "cpu value=1\ncpu value=2\ncpu value=3"
+	h.ServeHTTP(httptest.NewRecorder(), MustNewRequest("POST", "/write?db=metrics_write", strings.NewReader(body)))
+
+	w := httptest.NewRecorder()
+	h.ServeHTTP(w, MustNewRequest("GET", "/metrics?prefix=influxdb_write_", nil))
+	for _, exp := range []string{
+		`influxdb_write_points_total{db="metrics_write"} 2`,
+		`influxdb_write_points_rejected_total{db="metrics_write"} 1`,
+		fmt.Sprintf(`influxdb_write_bytes_total{db="metrics_write"} %d`, len(body)),
+		`influxdb_write_duration_seconds_count{db="metrics_write"} 1`,
+	} {
+		if !strings.Contains(w.Body.String(), exp) {
+			t.Fatalf("expected %q in body: %s", exp, w.Body.String())
+		}
+	}
+}
+
+// Ensure points that fail to parse are counted as rejected.
+func TestHandler_Metrics_WriteParseError(t *testing.T) {
+	c := httpd.NewConfig()
+	c.MaxWriteTimePast = toml.Duration(time.Hour)
+	h := NewHandlerWithConfig(c)
+	h.MetaClient.DatabaseFn = func(name string) *meta.DatabaseInfo {
+		return &meta.DatabaseInfo{Name: name}
+	}
+	h.PointsWriter.WritePointsFn = func(_, _ string, _ models.ConsistencyLevel, _ meta.User, points []models.Point) error {
+		return nil
+	}
+
+	for _, body := range []string{
+		"cpu value=1\ncpu value=\ncpu",
+		"cpu\nmem",
+	} {
+		w := httptest.NewRecorder()
+		h.ServeHTTP(w, MustNewRequest("POST", "/write?db=metrics_parse", strings.NewReader(body)))
+		if w.Code != http.StatusBadRequest {
+			t.Fatalf("unexpected status: %d", w.Code)
+		}
+	}
+
+	// Parse failures are counted when the parsed points are all outside the
+	// write window.
+	body := fmt.Sprintf("cpu value=1 %d\ncpu value=", time.Now().Add(-2*time.Hour).UnixNano())
+	w := httptest.NewRecorder()
+	h.ServeHTTP(w, MustNewRequest("POST", "/write?db=metrics_parse", strings.NewReader(body)))
+	if w.Code != http.StatusBadRequest {
+		t.Fatalf("unexpected status: %d", w.Code)
+	} else if !strings.Contains(w.Body.String(), "dropped=2") {
+		t.Fatalf("unexpected body: %s", w.Body.String())
+	}
+
+	w = httptest.NewRecorder()
+	h.ServeHTTP(w, MustNewRequest("GET", "/metrics?prefix=influxdb_write_", nil))
+	for _, exp := range []string{
+		`influxdb_write_points_total{db="metrics_parse"} 1`,
+		`influxdb_write_points_rejected_total{db="metrics_parse"} 6`,
+	} {
+		if !strings.Contains(w.Body.String(), exp) {
+			t.Fatalf("expected %q in body: %s", exp, w.Body.String())
+		}
+	}
+}
+
 // Ensure the handler handles status requests correctly.
 func TestHandler_Status(t *testing.T) {
 	h := NewHandler(false)
@@ -1609,6 +1896,90 @@ func TestHandler_Write_EntityTooLarge_NoContentLength(t *testing.T) {
 	}
 }
 
+// Ensure compressed write bodies are decoded and limited by max-body-size
+// after decompression.
+func TestHandler_Write_ContentEncoding(t *testing.T) {
+	compress := map[string]func(b []byte) []byte{
+		"gzip": func(b []byte) []byte {
+			var buf bytes.Buffer
+			zw := gzip.NewWriter(&buf)
+			zw.Write(b)
+			zw.Close()
+			return buf.Bytes()
+		},
+		"zstd": func(b []byte) []byte {
+			enc, _ := zstd.NewWriter(nil)
+			defer enc.Close()
+			return enc.EncodeAll(b, nil)
+		},
+		"snappy": func(b []byte) []byte {
+			var buf bytes.Buffer
+			sw := snappy.NewBufferedWriter(&buf)
+			sw.Write(b)
+			sw.Close()
+			return buf.Bytes()
+		},
+	}
+
+	for encoding, fn := range compress {
+		t.Run(encoding, func(t *testing.T) {
+			h := NewHandler(false)
+			h.Config.MaxBodySize = 1000
+			h.MetaClient.DatabaseFn = func(name string) *meta.DatabaseInfo {
+				return &meta.DatabaseInfo{}
+			}
+			var n int
+			h.PointsWriter.WritePointsFn = func(_, _ string, _ models.ConsistencyLevel, _ meta.User, points []models.Point) error {
+				n += len(points)
+				return nil
+			}
+
+			req := MustNewRequest("POST", "/write?db=foo", bytes.NewReader(fn([]byte("cpu value=1\ncpu value=2"))))
+			req.Header.Set("Content-Encoding", encoding)
+			w := httptest.NewRecorder()
+			h.ServeHTTP(w, req)
+			if w.Code != http.StatusNoContent {
+				t.Fatalf("unexpected status: %d: %s", w.Code, w.Body.String())
+			} else if n != 2 {
+				t.Fatalf("unexpected number of points written: %d", n)
+			}
+
+			// A small body that expands past the limit is rejected.
+			body := fn([]byte(strings.Repeat("cpu value=1\n", 1000)))
+			if len(body) >= h.Config.MaxBodySize {
+				t.Fatalf("compressed body too large for test: %d", len(body))
+			}
+			req = MustNewRequest("POST", "/write?db=foo", bytes.NewReader(body))
+			req.Header.Set("Content-Encoding", encoding)
+			w = httptest.NewRecorder()
+			h.ServeHTTP(w, req)
+			if w.Code != http.StatusRequestEntityTooLarge {
+				t.Fatalf("unexpected status: %d", w.Code)
+			}
+		})
+	}
+
+	// A streamed body larger than the limit is rejected even if it
+	// decompresses to nothing.
+	h := NewHandler(false)
+	h.Config.MaxBodySize = 1000
+	h.MetaClient.DatabaseFn = func(name string) *meta.DatabaseInfo {
+		return &meta.DatabaseInfo{}
+	}
+	var buf bytes.Buffer
+	for buf.Len() <= h.Config.MaxBodySize {
+		gzip.NewWriter(&buf).Close()
+	}
+	req := MustNewRequest("POST", "/write?db=foo", &buf)
+	req.Header.Set("Content-Encoding", "gzip")
+	req.ContentLength = -1
+	w := httptest.NewRecorder()
+	h.ServeHTTP(w, req)
+	if w.Code != http.StatusRequestEntityTooLarge {
+		t.Fatalf("unexpected status: %d", w.Code)
+	}
+}
+
 // TestHandler_Write_NegativeMaxBodySize verifies no error occurs if MaxBodySize is < 0
 func TestHandler_Write_NegativeMaxBodySize(t *testing.T) {
 	b := bytes.NewReader([]byte(`foo n=1`))
@@ -1633,6 +2004,134 @@ func TestHandler_Write_NegativeMaxBodySize(t *testing.T) {
 	}
 }
 
+// Ensure CSV bodies are mapped onto points using the query parameters.
+func TestHandler_Write_CSV(t *testing.T) {
+	h := NewHandler(false)
+	h.MetaClient.DatabaseFn = func(name string) *meta.DatabaseInfo {
+		return &meta.DatabaseInfo{}
+	}
+
+	var got []string
+	h.PointsWriter.WritePointsFn = func(_, _ string, _ models.ConsistencyLevel, _ meta.User, points []models.Point) error {
+		for _, p := range points {
+			got = append(got, p.String())
+		}
+		return nil
+	}
+
+	body := "time,host,usage,state,note\n" +
+		"1,server01,0.5,true,ok\n" +
+		"2000-01-01T00:00:00Z,server02,7,false,\n" +
+		"3,server03,1e3,TRUE,inf\n" +
+		"4,server04,-2,False,NaN\n" +
+		"5,server05,0x10,T,F\n"
+	req := MustNewRequest("POST", "/write?db=foo&precision=s&measurement=cpu&tags=host", strings.NewReader(body))
+	req.Header.Set("Content-Type", "text/csv")
+	w := httptest.NewRecorder()
+	h.ServeHTTP(w, req)
+	if w.Code != http.StatusNoContent {
+		t.Fatalf("unexpected status: %d: %s", w.Code, w.Body.String())
+	}
+
+	exp := []string{
+		`cpu,host=server01 note="ok",state=true,usage=0.5 1000000000`,
+		`cpu,host=server02 state=false,usage=7 946684800000000000`,
+		`cpu,host=server03 note="inf",state=true,usage=1000 3000000000`,
+		`cpu,host=server04 note="NaN",state=false,usage=-2 4000000000`,
+		`cpu,host=server05 note="F",state="T",usage="0x10" 5000000000`,
+	}
+	if !reflect.DeepEqual(got, exp) {
+		t.Fatalf("unexpected points:\ngot %v\nexp %v", got, exp)
+	}
+
+	// Rows that cannot be converted are reported as a partial write.
+	got = nil
+	req = MustNewRequest("POST", "/write?db=foo&measurement=cpu&fields=usage", strings.NewReader("time,usage\nyesterday,1\n2,3\n"))
+	req.Header.Set("Content-Type", "text/csv")
+	w = httptest.NewRecorder()
+	h.ServeHTTP(w, req)
+	if w.Code != http.StatusBadRequest {
+		t.Fatalf("unexpected status: %d", w.Code)
+	} else if !strings.Contains(w.Body.String(), "unable to parse row 2") {
+		t.Fatalf("unexpected body: %s", w.Body.String())
+	} else if len(got) != 1 {
+		t.Fatalf("unexpected points: %v", got)
+	}
+
+	// A measurement is required.
+	req = MustNewRequest("POST", "/write?db=foo", strings.NewReader(body))
+	req.Header.Set("Content-Type", "text/csv")
+	w = httptest.NewRecorder()
+	h.ServeHTTP(w, req)
+	if w.Code != http.StatusBadRequest {
+		t.Fatalf("unexpected status: %d", w.Code)
+	}
+}
+
+// TestHandler_Write_TimeWindow verifies points outside the write time window
+// are dropped and reported as a partial write.
+func TestHandler_Write_TimeWindow(t *testing.T) {
+	c := httpd.NewConfig()
+	c.MaxWriteTimePast = toml.Duration(time.Hour)
+	c.MaxWriteTimeFuture = toml.Duration(time.Minute)
+	h := NewHandlerWithConfig(c)
+	h.MetaClient.DatabaseFn = func(name string) *meta.DatabaseInfo {
+		return &meta.DatabaseInfo{}
+	}
+
+	var got []models.Point
+	h.PointsWriter.WritePointsFn = func(_, _ string, _ models.ConsistencyLevel, _ meta.User, points []models.Point) error {
+		got = append(got, points...)
+		return nil
+	}
+
+	now := time.Now()
+	body := fmt.Sprintf("cpu value=1 %d\ncpu value=2 %d\ncpu value=3 %d\ncpu value=4",
+		now.Add(-2*time.Hour).UnixNano(), now.Add(-time.Minute).UnixNano(), now.Add(time.Hour).UnixNano())
+	w := httptest.NewRecorder()
+	h.ServeHTTP(w, MustNewRequest("POST", "/write?db=foo", strings.NewReader(body)))
+	if w.Code != http.StatusBadRequest {
+		t.Fatalf("unexpected status: %d", w.Code)
+	} else if exp := `{"error":"partial write: points older than 1h0m0s or more than 1m0s in the future dropped=2","code":"partial_write"}`; strings.TrimSpace(w.Body.String()) != exp {
+		t.Fatalf("unexpected body: %s", w.Body.String())
+	} else if len(got) != 2 {
+		t.Fatalf("unexpected points written: %v", got)
+	}
+
+	// Nothing is written if every point is outside the window.
+	got = nil
+	w = httptest.NewRecorder()
+	h.ServeHTTP(w, MustNewRequest("POST", "/write?db=foo&precision=s", strings.NewReader("cpu value=1 0")))
+	if w.Code != http.StatusBadRequest {
+		t.Fatalf("unexpected status: %d", w.Code)
+	} else if !strings.Contains(w.Body.String(), "dropped=1") {
+		t.Fatalf("unexpected body: %s", w.Body.String())
+	} else if got != nil {
+		t.Fatalf("unexpected points written: %v", got)
+	}
+}
+
+// TestHandler_Write_TimeWindow_Clamped verifies that a window reaching past the
+// range of timestamps does not drop points.
+func TestHandler_Write_TimeWindow_Clamped(t *testing.T) {
+	c := httpd.NewConfig()
+	c.MaxWriteTimePast = toml.Duration(math.MaxInt64)
+	c.MaxWriteTimeFuture = toml.Duration(math.MaxInt64)
+	h := NewHandlerWithConfig(c)
+	h.MetaClient.DatabaseFn = func(name string) *meta.DatabaseInfo {
+		return &meta.DatabaseInfo{}
+	}
+	h.PointsWriter.WritePointsFn = func(_, _ string, _ models.ConsistencyLevel, _ meta.User, _ []models.Point) error {
+		return nil
+	}
+
+	w := httptest.NewRecorder()
+	h.ServeHTTP(w, MustNewRequest("POST", "/write?db=foo", strings.NewReader("cpu value=1\ncpu value=2 0")))
+	if w.Code != http.StatusNoContent {
+		t.Fatalf("unexpected status: %d: %s", w.Code, w.Body.String())
+	}
+}
+
 // TestHandler_Write_V1_Precision verifies v1 writes validate precision.
 func TestHandler_Write_V1_Precision(t *testing.T) {
 	h := NewHandler(false)
@@ -1714,6 +2213,473 @@ func TestHandler_Write_V2_Precision(t *testing.T) {
 	}
 }
 
+// TestHandler_KillSwitch verifies writes and queries can be disabled per database.
+func TestHandler_KillSwitch(t *testing.T) {
+	h := NewHandler(false)
+	h.MetaClient.DatabaseFn = func(name string) *meta.DatabaseInfo {
+		if name == "missing" {
+			return nil
+		}
+		return &meta.DatabaseInfo{Name: name}
+	}
+	h.PointsWriter.WritePointsFn = func(_, _ string, _ models.ConsistencyLevel, _ meta.User, _ []models.Point) error {
+		return nil
+	}
+	h.StatementExecutor.ExecuteStatementFn = func(stmt influxql.Statement, ctx *query.ExecutionContext) error {
+		return ctx.Send(&query.Result{StatementID: 0})
+	}
+
+	do := func(method, url, body string) *httptest.ResponseRecorder {
+		w := httptest.NewRecorder()
+		h.ServeHTTP(w, MustNewRequest(method, url, strings.NewReader(body)))
+		return w
+	}
+
+	if w := do("POST", "/api/v1/killswitch", `{"db":"missing","writes":true}`); w.Code != http.StatusNotFound {
+		t.Fatalf("unexpected status: %d", w.Code)
+	}
+	if w := do("POST", "/api/v1/killswitch", `{"writes":true}`); w.Code != http.StatusBadRequest {
+		t.Fatalf("unexpected status: %d", w.Code)
+	}
+	if w := do("POST", "/api/v1/killswitch", `{"db":"foo","writes":true,"reason":"incident 42"}`); w.Code != http.StatusNoContent {
+		t.Fatalf("unexpected status: %d: %s", w.Code, w.Body)
+	}
+
+	if w := do("POST", "/write?db=foo", `cpu value=1`); w.Code != http.StatusForbidden {
+		t.Fatalf("unexpected status: %d", w.Code)
+	} else if !strings.Contains(w.Body.String(), "incident 42") {
+		t.Fatalf("expected reason in body, got: %s", w.Body)
+	}
+	if w := do("POST", "/api/v2/write?bucket=foo", `cpu value=1`); w.Code != http.StatusForbidden {
+		t.Fatalf("unexpected status: %d", w.Code)
+	}
+	if w := do("POST", "/write?db=bar", `cpu value=1`); w.Code != http.StatusNoContent {
+		t.Fatalf("unexpected status: %d", w.Code)
+	}
+	if w := do("GET", "/query?db=foo&q=SELECT+*+FROM+cpu", ""); w.Code != http.StatusOK {
+		t.Fatalf("unexpected status: %d", w.Code)
+	}
+
+	if w := do("POST", "/api/v1/killswitch", `{"db":"foo","queries":true}`); w.Code != http.StatusNoContent {
+		t.Fatalf("unexpected status: %d", w.Code)
+	}
+	if w := do("GET", "/query?db=foo&q=SELECT+*+FROM+cpu", ""); w.Code != http.StatusForbidden {
+		t.Fatalf("unexpected status: %d", w.Code)
+	} else if !strings.Contains(w.Body.String(), httpd.DefaultKillSwitchReason) {
+		t.Fatalf("expected default reason in body, got: %s", w.Body)
+	}
+	if w := do("GET", "/query?db=bar&q=SELECT+*+FROM+foo..cpu", ""); w.Code != http.StatusForbidden {
+		t.Fatalf("unexpected status: %d", w.Code)
+	}
+	if w := do("POST", "/write?db=foo", `cpu value=1`); w.Code != http.StatusNoContent {
+		t.Fatalf("unexpected status: %d", w.Code)
+	}
+
+	w := do("GET", "/api/v1/killswitch", "")
+	if w.Code != http.StatusOK {
+		t.Fatalf("unexpected status: %d", w.Code)
+	}
+	var got struct {
+		KillSwitches []httpd.KillSwitchState `json:"killSwitches"`
+	}
+	if err := json.Unmarshal(w.Body.Bytes(), &got); err != nil {
+		t.Fatal(err)
+	}
+	exp := []httpd.KillSwitchState{{Database: "foo", Queries: true, Reason: httpd.DefaultKillSwitchReason}}
+	if !reflect.DeepEqual(got.KillSwitches, exp) {
+		t.Fatalf("unexpected kill switches: %#v", got.KillSwitches)
+	}
+
+	if w := do("DELETE", "/api/v1/killswitch?db=foo", ""); w.Code != http.StatusNoContent {
+		t.Fatalf("unexpected status: %d", w.Code)
+	}
+	if w := do("GET", "/query?db=foo&q=SELECT+*+FROM+cpu", ""); w.Code != http.StatusOK {
+		t.Fatalf("unexpected status: %d", w.Code)
+	}
+}
+
+// TestHandler_KillSwitch_FluxAndDelete verifies kill switches apply to Flux
+// queries, v2 deletes and InfluxQL statements that write.
+func TestHandler_KillSwitch_FluxAndDelete(t *testing.T) {
+	h := NewHandlerWithConfig(NewHandlerConfig(WithFlux(), WithNoLog()))
+	h.MetaClient.DatabaseFn = func(name string) *meta.DatabaseInfo {
+		return &meta.DatabaseInfo{
+			Name:              name,
+			RetentionPolicies: []meta.RetentionPolicyInfo{{Name: "autogen"}},
+		}
+	}
+	h.Store.DeleteFn = func(database string, sources []influxql.Source, condition influxql.Expr) error { return nil }
+
+	h.StatementExecutor.ExecuteStatementFn = func(stmt influxql.Statement, ctx *query.ExecutionContext) error {
+		return ctx.Send(&query.Result{StatementID: 0})
+	}
+
+	var (
+		fluxDB        string
+		fluxPrivilege influxql.Privilege
+	)
+	h.Controller.QueryFn = func(ctx context.Context, compiler flux.Compiler) (flux.Query, error) {
+		check := influxdb2.DatabaseCheckFromContext(ctx)
+		if check == nil {
+			t.Fatal("expected a database check in the Flux query context")
+		} else if err := check(fluxDB, fluxPrivilege); err != nil {
+			return nil, fmt.Errorf("error calling function %q: %w", "from", err)
+		}
+		p := &mock.Program{}
+		return p.Start(ctx, nil)
+	}
+
+	for _, body := range []string{`{"db":"foo","writes":true,"queries":true}`, `{"db":"baz","writes":true}`} {
+		w := httptest.NewRecorder()
+		h.ServeHTTP(w, MustNewRequest("POST", "/api/v1/killswitch", strings.NewReader(body)))
+		if w.Code != http.StatusNoContent {
+			t.Fatalf("unexpected status: %d", w.Code)
+		}
+	}
+
+	for _, tt := range []struct {
+		db        string
+		privilege influxql.Privilege
+		status    int
+	}{
+		{"foo", influxql.ReadPrivilege, http.StatusForbidden},
+		{"foo", influxql.WritePrivilege, http.StatusForbidden},
+		{"baz", influxql.WritePrivilege, http.StatusForbidden},
+		{"baz", influxql.ReadPrivilege, http.StatusOK},
+		{"bar", influxql.WritePrivilege, http.StatusOK},
+	} {
+		fluxDB, fluxPrivilege = tt.db, tt.privilege
+		req := MustNewJSONRequest("POST", "/api/v2/query", strings.NewReader(`{"query":"foo"}`))
+		req.Header.Set("Content-Type", "application/json")
+		w := httptest.NewRecorder()
+		h.ServeHTTP(w, req)
+		if w.Code != tt.status {
+			t.Fatalf("unexpected Flux status for %s on %q: exp %d, got %d: %s", tt.privilege, tt.db, tt.status, w.Code, w.Body)
+		} else if tt.status == http.StatusForbidden && w.Header().Get("X-InfluxDB-Error-Code") != "forbidden" {
+			t.Fatalf("unexpected error code: %q", w.Header().Get("X-InfluxDB-Error-Code"))
+		}
+	}
+
+	// InfluxQL statements that write are rejected when writes are disabled.
+	for _, tt := range []struct {
+		url    string
+		status int
+	}{
+		{"/query?db=baz&q=DELETE+FROM+cpu", http.StatusForbidden},
+		{"/query?db=baz&q=DROP+SERIES+FROM+cpu", http.StatusForbidden},
+		{"/query?db=bar&q=SELECT+*+INTO+baz..cpu+FROM+cpu", http.StatusForbidden},
+		{"/query?db=baz&q=SELECT+*+FROM+cpu", http.StatusOK},
+		{"/query?db=bar&q=DELETE+FROM+cpu", http.StatusOK},
+	} {
+		w := httptest.NewRecorder()
+		h.ServeHTTP(w, MustNewJSONRequest("POST", tt.url, nil))
+		if w.Code != tt.status {
+			t.Fatalf("unexpected status for %s: exp %d, got %d: %s", tt.url, tt.status, w.Code, w.Body)
+		}
+	}
+
+	body := `{"start":"2022-03-23T18:56:06Z","stop":"2022-03-23T20:56:06Z"}`
+	w := httptest.NewRecorder()
+	h.ServeHTTP(w, MustNewJSONRequest("POST", "/api/v2/delete?bucket=foo/autogen", strings.NewReader(body)))
+	if w.Code != http.StatusForbidden {
+		t.Fatalf("unexpected delete status: %d: %s", w.Code, w.Body)
+	}
+	w = httptest.NewRecorder()
+	h.ServeHTTP(w, MustNewJSONRequest("POST", "/api/v2/delete?bucket=bar/autogen", strings.NewReader(body)))
+	if w.Code != http.StatusOK {
+		t.Fatalf("unexpected delete status: %d: %s", w.Code, w.Body)
+	}
+}
+
+// TestHandler_KillSwitch_Auth verifies only admins can manage kill switches.
+func TestHandler_KillSwitch_Auth(t *testing.T) {
+	h := NewHandler(true)
+	h.MetaClient.AdminUserExistsFn = func() bool { return true }
+	h.MetaClient.DatabaseFn = func(name string) *meta.DatabaseInfo {
+		return &meta.DatabaseInfo{Name: name}
+	}
+	h.MetaClient.AuthenticateFn = func(u, p string) (meta.User, error) {
+		return &meta.UserInfo{Name: u, Admin: u == "admin"}, nil
+	}
+
+	for _, tt := range []struct {
+		user   string
+		status int
+	}{
+		{"user", http.StatusForbidden},
+		{"admin", http.StatusNoContent},
+	} {
+		w := httptest.NewRecorder()
+		req := MustNewRequest("POST", "/api/v1/killswitch", strings.NewReader(`{"db":"foo","writes":true}`))
+		req.SetBasicAuth(tt.user, "password")
+		h.ServeHTTP(w, req)
+		if w.Code != tt.status {
+			t.Fatalf("unexpected status for %q: exp %d, got %d", tt.user, tt.status, w.Code)
+		}
+	}
+}
+
+// TestHandler_AuditLog verifies that mutating requests and rejected requests
+// are recorded in the audit log.
+func TestHandler_AuditLog(t *testing.T) {
+	path := filepath.Join(t.TempDir(), "audit.log")
+	h := NewHandlerWithConfig(NewHandlerConfig(WithAuthentication(), WithFlux(), func(c *httpd.Config) {
+		c.AuditLogPath = path
+	}))
+	h.MetaClient.AdminUserExistsFn = func() bool { return true }
+	h.MetaClient.DatabaseFn = func(name string) *meta.DatabaseInfo {
+		return &meta.DatabaseInfo{Name: name}
+	}
+	h.MetaClient.AuthenticateFn = func(u, p string) (meta.User, error) {
+		return &meta.UserInfo{Name: u, Admin: u == "admin"}, nil
+	}
+	h.QueryAuthorizer.AuthorizeQueryFn = func(u meta.User, q *influxql.Query, db string) error {
+		return nil
+	}
+	h.StatementExecutor.ExecuteStatementFn = func(stmt influxql.Statement, ctx *query.ExecutionContext) error {
+		return ctx.Send(&query.Result{StatementID: 0})
+	}
+	h.Controller.QueryFn = func(ctx context.Context, compiler flux.Compiler) (flux.Query, error) {
+		// The query writes to bar with to().
+		if err := influxdb2.DatabaseCheckFromContext(ctx)("bar", influxql.WritePrivilege); err != nil {
+			return nil, err
+		}
+		p := &mock.Program{}
+		return p.Start(ctx, nil)
+	}
+	h.Open()
+	defer h.Close()
+
+	for _, tt := range []struct {
+		user   string
+		method string
+		url    string
+		body   string
+	}{
+		{"user", "POST", "/api/v1/killswitch", `{"db":"foo","writes":true}`},
+		{"admin", "POST", "/api/v1/killswitch", `{"db":"foo","writes":true}`},
+		{"admin", "GET", "/api/v1/killswitch", ""},
+		{"admin", "GET", "/query?db=foo&q=SELECT+*+FROM+cpu", ""},
+		{"admin", "POST", "/query?q=DROP+DATABASE+foo", ""},
+		{"admin", "POST", "/api/v2/query", `{"query":"foo"}`},
+	} {
+		req := MustNewRequest(tt.method, tt.url, strings.NewReader(tt.body))
+		if tt.url == "/api/v2/query" {
+			req.Header.Set("Content-Type", "application/json")
+		}
+		req.SetBasicAuth(tt.user, "password")
+		h.ServeHTTP(httptest.NewRecorder(), req)
+	}
+
+	b, err := os.ReadFile(path)
+	if err != nil {
+		t.Fatal(err)
+	}
+
+	type entry struct {
+		User      string `json:"user"`
+		Operation string `json:"op"`
+		Database  string `json:"db"`
+		Status    int    `json:"status"`
+		Outcome   string `json:"outcome"`
+	}
+	var got []entry
+	for _, line := range strings.Split(strings.TrimSpace(string(b)), "\n") {
+		var e entry
+		if err := json.Unmarshal([]byte(line), &e); err != nil {
+			t.Fatalf("unable to decode audit entry %q: %s", line, err)
+		}
+		got = append(got, e)
+	}
+
+	exp := []entry{
+		{User: "user", Operation: "set-kill-switch", Status: http.StatusForbidden, Outcome: "denied"},
+		{User: "admin", Operation: "set-kill-switch", Database: "foo", Status: http.StatusNoContent, Outcome: "success"},
+		{User: "admin", Operation: "query", Database: "foo", Status: http.StatusOK, Outcome: "success"},
+		{User: "admin", Operation: "flux-read", Database: "bar", Status: http.StatusOK, Outcome: "success"},
+	}
+	if !reflect.DeepEqual(got, exp) {
+		t.Fatalf("unexpected audit entries:\ngot %+v\nexp %+v", got, exp)
+	}
+}
+
+// TestHandler_Drain verifies that writes are rejected once the handler is
+// draining, while queries continue to be served, and that draining can be
+// stopped.
+func TestHandler_Drain(t *testing.T) {
+	h := NewHandlerWithConfig(NewHandlerConfig(WithFlux(), WithNoLog()))
+	h.MetaClient.DatabaseFn = func(name string) *meta.DatabaseInfo {
+		return &meta.DatabaseInfo{Name: name}
+	}
+	h.PointsWriter.WritePointsFn = func(_, _ string, _ models.ConsistencyLevel, _ meta.User, _ []models.Point) error {
+		return nil
+	}
+	h.StatementExecutor.ExecuteStatementFn = func(stmt influxql.Statement, ctx *query.ExecutionContext) error {
+		return ctx.Send(&query.Result{StatementID: 0})
+	}
+	h.Controller.QueryFn = func(ctx context.Context, compiler flux.Compiler) (flux.Query, error) {
+		// The query writes to foo with to().
+		if err := influxdb2.DatabaseCheckFromContext(ctx)("foo", influxql.WritePrivilege); err != nil {
+			return nil, fmt.Errorf("error calling function %q: %w", "to", err)
+		}
+		p := &mock.Program{}
+		return p.Start(ctx, nil)
+	}
+
+	w := httptest.NewRecorder()
+	h.ServeHTTP(w, MustNewRequest("POST", "/api/v1/drain", nil))
+	if w.Code != http.StatusNoContent {
+		t.Fatalf("unexpected status: %d", w.Code)
+	} else if !h.Draining() {
+		t.Fatal("expected handler to be draining")
+	}
+
+	w = httptest.NewRecorder()
+	h.ServeHTTP(w, MustNewRequest("POST", "/write?db=foo", strings.NewReader("cpu value=1")))
+	if w.Code != http.StatusServiceUnavailable {
+		t.Fatalf("unexpected write status: %d", w.Code)
+	} else if got := w.Header().Get("Retry-After"); got != "10" {
+		t.Fatalf("unexpected Retry-After: %q", got)
+	}
+
+	w = httptest.NewRecorder()
+	h.ServeHTTP(w, MustNewRequest("GET", "/health", nil))
+	if w.Code != http.StatusServiceUnavailable {
+		t.Fatalf("unexpected health status: %d", w.Code)
+	}
+
+	// Deletes and queries that write are rejected as well.
+	fluxReq := MustNewJSONRequest("POST", "/api/v2/query", strings.NewReader(`{"query":"foo"}`))
+	fluxReq.Header.Set("Content-Type", "application/json")
+	for _, req := range []*http.Request{
+		MustNewJSONRequest("POST", "/query?db=foo&q=DELETE+FROM+cpu", nil),
+		MustNewJSONRequest("POST", "/query?db=foo&q=SELECT+*+INTO+cpu2+FROM+cpu", nil),
+		MustNewJSONRequest("POST", "/api/v2/delete?bucket=foo", strings.NewReader(`{"start":"2022-03-23T18:56:06Z","stop":"2022-03-23T20:56:06Z"}`)),
+		fluxReq,
+	} {
+		w = httptest.NewRecorder()
+		h.ServeHTTP(w, req)
+		if w.Code != http.StatusServiceUnavailable {
+			t.Fatalf("unexpected status for %s: %d: %s", req.URL, w.Code, w.Body)
+		} else if got := w.Header().Get("Retry-After"); got != "10" {
+			t.Fatalf("unexpected Retry-After for %s: %q", req.URL, got)
+		}
+	}
+
+	w = httptest.NewRecorder()
+	h.ServeHTTP(w, MustNewJSONRequest("GET", "/query?db=foo&q=SELECT+*+FROM+cpu", nil))
+	if w.Code != http.StatusOK {
+		t.Fatalf("unexpected query status: %d", w.Code)
+	}
+
+	// Draining can be stopped.
+	w = httptest.NewRecorder()
+	h.ServeHTTP(w, MustNewRequest("DELETE", "/api/v1/drain", nil))
+	if w.Code != http.StatusNoContent {
+		t.Fatalf("unexpected status: %d", w.Code)
+	} else if h.Draining() {
+		t.Fatal("expected handler to accept writes")
+	}
+
+	w = httptest.NewRecorder()
+	h.ServeHTTP(w, MustNewRequest("POST", "/write?db=foo", strings.NewReader("cpu value=1")))
+	if w.Code != http.StatusNoContent {
+		t.Fatalf("unexpected write status: %d", w.Code)
+	}
+
+	// A zero drain-timeout falls back to the default.
+	h.Config.DrainTimeout = 0
+	if got := h.DrainTimeout(); got != httpd.DefaultDrainTimeout {
+		t.Fatalf("unexpected drain timeout: %s", got)
+	}
+}
+
+// TestHandler_RateLimit verifies per-database write and query rate limits.
+func TestHandler_RateLimit(t *testing.T) {
+	c := httpd.NewConfig()
+	c.DatabaseWriteRateLimit = 10
+	c.DatabaseQueryRateLimit = 1
+	c.FluxEnabled = true
+	h := NewHandlerWithConfig(c)
+	h.MetaClient.DatabaseFn = func(name string) *meta.DatabaseInfo {
+		if name == "missing" {
+			return nil
+		}
+		return &meta.DatabaseInfo{Name: name}
+	}
+	h.PointsWriter.WritePointsFn = func(_, _ string, _ models.ConsistencyLevel, _ meta.User, _ []models.Point) error {
+		return nil
+	}
+	h.StatementExecutor.ExecuteStatementFn = func(stmt influxql.Statement, ctx *query.ExecutionContext) error {
+		return ctx.Send(&query.Result{StatementID: 0})
+	}
+
+	do := func(method, url, body string) *httptest.ResponseRecorder {
+		w := httptest.NewRecorder()
+		h.ServeHTTP(w, MustNewRequest(method, url, strings.NewReader(body)))
+		return w
+	}
+
+	// The first write overdraws the 10 byte/s budget for foo.
+	if w := do("POST", "/write?db=foo", "cpu,host=server01 value=1"); w.Code != http.StatusNoContent {
+		t.Fatalf("unexpected status: %d", w.Code)
+	}
+	if w := do("POST", "/write?db=foo", "cpu,host=server01 value=1"); w.Code != http.StatusTooManyRequests {
+		t.Fatalf("unexpected status: %d", w.Code)
+	} else if got := w.Header().Get("Retry-After"); got != "2" {
+		t.Fatalf("unexpected Retry-After: %q", got)
+	} else if got := w.Header().Get("X-RateLimit-Limit"); got != "10" {
+		t.Fatalf("unexpected X-RateLimit-Limit: %q", got)
+	}
+	if w := do("POST", "/write?db=bar", "cpu,host=server01 value=1"); w.Code != http.StatusNoContent {
+		t.Fatalf("unexpected status: %d", w.Code)
+	}
+
+	if w := do("GET", "/query?db=foo&q=SELECT+*+FROM+cpu", ""); w.Code != http.StatusOK {
+		t.Fatalf("unexpected status: %d", w.Code)
+	}
+	if w := do("GET", "/query?db=foo&q=SELECT+*+FROM+cpu", ""); w.Code != http.StatusTooManyRequests {
+		t.Fatalf("unexpected status: %d", w.Code)
+	}
+
+	// Fully qualified measurements are limited by their own database.
+	if w := do("GET", "/query?db=bar&q=SELECT+*+FROM+foo..cpu", ""); w.Code != http.StatusTooManyRequests {
+		t.Fatalf("unexpected status: %d", w.Code)
+	}
+
+	// Databases that do not exist are not limited.
+	for i := 0; i < 2; i++ {
+		if w := do("GET", "/query?db=missing&q=SELECT+*+FROM+cpu", ""); w.Code != http.StatusOK {
+			t.Fatalf("unexpected status: %d", w.Code)
+		}
+	}
+
+	// Flux queries share the query budget of each bucket's database.
+	h.Controller.QueryFn = func(ctx context.Context, compiler flux.Compiler) (flux.Query, error) {
+		if err := influxdb2.DatabaseCheckFromContext(ctx)("foo", influxql.ReadPrivilege); err != nil {
+			return nil, fmt.Errorf("error calling function %q: %w", "from", err)
+		}
+		p := &mock.Program{}
+		return p.Start(ctx, nil)
+	}
+	req := MustNewJSONRequest("POST", "/api/v2/query", strings.NewReader(`{"query":"foo"}`))
+	req.Header.Set("Content-Type", "application/json")
+	w := httptest.NewRecorder()
+	h.ServeHTTP(w, req)
+	if w.Code != http.StatusTooManyRequests {
+		t.Fatalf("unexpected status: %d: %s", w.Code, w.Body)
+	} else if got := w.Header().Get("Retry-After"); got == "" {
+		t.Fatal("expected Retry-After")
+	} else if got := w.Header().Get("X-RateLimit-Limit"); got != "1" {
+		t.Fatalf("unexpected X-RateLimit-Limit: %q", got)
+	}
+
+	stats := h.Statistics(nil)
+	if got := stats[0].Values["rateLimitedReq"]; got != int64(4) {
+		t.Fatalf("unexpected rateLimitedReq: %v", got)
+	}
+}
+
 func TestHandler_Delete_V2(t *testing.T) {
 	type test struct {
 		url    string
@@ -2543,6 +3509,43 @@ func TestThrottler_Handler(t *testing.T) {
 		<-end
 	})
 
+	t.Run("ErrDeadline", func(t *testing.T) {
+		throttler := httpd.NewThrottler(1, 1)
+
+		begin, end := make(chan struct{}), make(chan struct{})
+		h := throttler.Handler(http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+			begin <- struct{}{}
+			end <- struct{}{}
+		}))
+
+		// First request should execute immediately.
+		go func() { h.ServeHTTP(nil, nil) }()
+		<-begin
+
+		// Second request should be enqueued until its deadline passes.
+		ctx, cancel := context.WithTimeout(context.Background(), time.Millisecond)
+		defer cancel()
+		w := httptest.NewRecorder()
+		h.ServeHTTP(w, MustNewRequest("GET", "/", nil).WithContext(ctx))
+		if w.Code != http.StatusGatewayTimeout {
+			t.Fatalf("unexpected status code: %d", w.Code)
+		} else if body := w.Body.String(); body != "request throttled, deadline exceeded\n" {
+			t.Fatalf("unexpected response body: %q", body)
+		}
+
+		// A canceled request gets no response.
+		ctx, cancel = context.WithCancel(context.Background())
+		cancel()
+		w = httptest.NewRecorder()
+		h.ServeHTTP(w, MustNewRequest("GET", "/", nil).WithContext(ctx))
+		if w.Body.Len() != 0 {
+			t.Fatalf("unexpected response body: %q", w.Body.String())
+		}
+
+		// Allow the existing request to complete.
+		<-end
+	})
+
 	t.Run("ErrFull", func(t *testing.T) {
 		delay := 100 * time.Millisecond
 		if os.Getenv("CI") != "" {
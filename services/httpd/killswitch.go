@@ -0,0 +1,96 @@
+package httpd
+
+import (
+	"sort"
+	"sync"
+)
+
+// DefaultKillSwitchReason is reported to clients when a kill switch was
+// enabled without an explicit reason.
+const DefaultKillSwitchReason = "disabled by an administrator"
+
+// KillSwitchState describes which operations are disabled for a database.
+type KillSwitchState struct {
+	Database string `json:"db"`
+	Writes   bool   `json:"writes"`
+	Queries  bool   `json:"queries"`
+	Reason   string `json:"reason,omitempty"`
+}
+
+// KillSwitches tracks databases for which writes and/or queries have been
+// temporarily disabled at runtime. It is intended for incident response when
+// the workload of a single database is destabilizing the instance. The state
+// is held in memory only and is lost when the server restarts.
+type KillSwitches struct {
+	mu  sync.RWMutex
+	dbs map[string]KillSwitchState
+}
+
+// NewKillSwitches returns an empty set of kill switches.
+func NewKillSwitches() *KillSwitches {
+	return &KillSwitches{
+		dbs: make(map[string]KillSwitchState),
+	}
+}
+
+// Set replaces the kill switch state for a database. Clearing both the
+// writes and queries flags removes the database from the set.
+func (k *KillSwitches) Set(state KillSwitchState) {
+	if state.Reason == "" {
+		state.Reason = DefaultKillSwitchReason
+	}
+
+	k.mu.Lock()
+	defer k.mu.Unlock()
+	if !state.Writes && !state.Queries {
+		delete(k.dbs, state.Database)
+		return
+	}
+	k.dbs[state.Database] = state
+}
+
+// Clear re-enables writes and queries for a database.
+func (k *KillSwitches) Clear(database string) {
+	k.mu.Lock()
+	delete(k.dbs, database)
+	k.mu.Unlock()
+}
+
+// WritesDisabled returns the reason writes are disabled for the database and
+// whether they are disabled at all.
+func (k *KillSwitches) WritesDisabled(database string) (string, bool) {
+	k.mu.RLock()
+	defer k.mu.RUnlock()
+	state, ok := k.dbs[database]
+	if !ok || !state.Writes {
+		return "", false
+	}
+	return state.Reason, true
+}
+
+// QueriesDisabled returns the reason queries are disabled for the database
+// and whether they are disabled at all.
+func (k *KillSwitches) QueriesDisabled(database string) (string, bool) {
+	k.mu.RLock()
+	defer k.mu.RUnlock()
+	state, ok := k.dbs[database]
+	if !ok || !state.Queries {
+		return "", false
+	}
+	return state.Reason, true
+}
+
+// States returns the current kill switch states sorted by database name.
+func (k *KillSwitches) States() []KillSwitchState {
+	k.mu.RLock()
+	states := make([]KillSwitchState, 0, len(k.dbs))
+	for _, state := range k.dbs {
+		states = append(states, state)
+	}
+	k.mu.RUnlock()
+
+	sort.Slice(states, func(i, j int) bool {
+		return states[i].Database < states[j].Database
+	})
+	return states
+}
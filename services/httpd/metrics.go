@@ -0,0 +1,124 @@
+package httpd
+
+import (
+	"net/http"
+	"strings"
+
+	"github.com/prometheus/client_golang/prometheus"
+	"github.com/prometheus/client_golang/prometheus/promhttp"
+	dto "github.com/prometheus/client_model/go"
+)
+
+// newMetricsHandler returns the handler for the /metrics endpoint. The
+// metrics exposed can be restricted with one or more name and prefix query
+// parameters, and the OpenMetrics format is served to clients that ask for it
+// through the Accept header.
+func newMetricsHandler() http.Handler {
+	return promhttp.InstrumentMetricHandler(
+		prometheus.DefaultRegisterer,
+		http.HandlerFunc(func(w http.ResponseWriter, r *http.Request) {
+			var gatherer prometheus.Gatherer = prometheus.DefaultGatherer
+			q := r.URL.Query()
+			if names, prefixes := q["name"], q["prefix"]; len(names) > 0 || len(prefixes) > 0 {
+				gatherer = &metricsFilter{
+					gatherer: gatherer,
+					names:    names,
+					prefixes: prefixes,
+				}
+			}
+
+			promhttp.HandlerFor(gatherer, promhttp.HandlerOpts{
+				EnableOpenMetrics: true,
+			}).ServeHTTP(w, r)
+		}),
+	)
+}
+
+// metricsFilter is a prometheus.Gatherer that only returns the metric
+// families matching one of the requested names or name prefixes.
+type metricsFilter struct {
+	gatherer prometheus.Gatherer
+	names    []string
+	prefixes []string
+}
+
+// Gather implements prometheus.Gatherer.
+func (f *metricsFilter) Gather() ([]*dto.MetricFamily, error) {
+	mfs, err := f.gatherer.Gather()
+	filtered := mfs[:0]
+	for _, mf := range mfs {
+		if f.match(mf.GetName()) {
+			filtered = append(filtered, mf)
+		}
+	}
+	return filtered, err
+}
+
+func (f *metricsFilter) match(name string) bool {
+	for _, n := range f.names {
+		if name == n {
+			return true
+		}
+	}
+	for _, p := range f.prefixes {
+		if strings.HasPrefix(name, p) {
+			return true
+		}
+	}
+	return false
+}
+
+// Per-database write metrics. Only databases that exist are used as label
+// values, so the number of series is bounded by the number of databases.
+// The series of a dropped database are kept until the process restarts.
+var (
+	writePointsTotal = prometheus.NewCounterVec(prometheus.CounterOpts{
+		Namespace: "influxdb",
+		Subsystem: "write",
+		Name:      "points_total",
+		Help:      "Number of points written.",
+	}, []string{"db"})
+
+	writePointsRejectedTotal = prometheus.NewCounterVec(prometheus.CounterOpts{
+		Namespace: "influxdb",
+		Subsystem: "write",
+		Name:      "points_rejected_total",
+		Help:      "Number of points that failed to be written or were dropped.",
+	}, []string{"db"})
+
+	writeBytesTotal = prometheus.NewCounterVec(prometheus.CounterOpts{
+		Namespace: "influxdb",
+		Subsystem: "write",
+		Name:      "bytes_total",
+		Help:      "Number of request body bytes received by write requests.",
+	}, []string{"db"})
+
+	writeDurationSeconds = prometheus.NewHistogramVec(prometheus.HistogramOpts{
+		Namespace: "influxdb",
+		Subsystem: "write",
+		Name:      "duration_seconds",
+		Help:      "Time taken to serve write requests.",
+		Buckets:   prometheus.DefBuckets,
+	}, []string{"db"})
+)
+
+func init() {
+	prometheus.MustRegister(writePointsTotal, writePointsRejectedTotal, writeBytesTotal, writeDurationSeconds)
+}
+
+// dbWriteMetrics holds the write metrics of a single database.
+type dbWriteMetrics struct {
+	points   prometheus.Counter
+	rejected prometheus.Counter
+	bytes    prometheus.Counter
+	duration prometheus.Observer
+}
+
+func newDBWriteMetrics(database string) dbWriteMetrics {
+	return dbWriteMetrics{
+		points:   writePointsTotal.WithLabelValues(database),
+		rejected: writePointsRejectedTotal.WithLabelValues(database),
+		bytes:    writeBytesTotal.WithLabelValues(database),
+		duration: writeDurationSeconds.WithLabelValues(database),
+	}
+}
@@ -0,0 +1,101 @@
+package httpd
+
+import (
+	"math"
+	"sync"
+	"time"
+)
+
+// rateLimiterSweepInterval is how often idle buckets are removed.
+const rateLimiterSweepInterval = time.Minute
+
+// RateLimiter maintains a token bucket per database. A request is admitted
+// while its database's bucket has tokens left and is then charged its full
+// cost, so the bucket may be overdrawn by a single large request. Later
+// requests are rejected until the debt has been paid back at the configured
+// rate. This lets the byte size of a write be charged after the body has been
+// read, when the size is actually known.
+type RateLimiter struct {
+	mu        sync.Mutex
+	rate      float64
+	buckets   map[string]*tokenBucket
+	lastSweep time.Time
+
+	// now returns the current time. It can be replaced in tests.
+	now func() time.Time
+}
+
+type tokenBucket struct {
+	tokens float64
+	last   time.Time
+}
+
+// NewRateLimiter returns a RateLimiter that refills each database's bucket
+// at perSecond tokens per second. Buckets hold at most one second's worth of
+// tokens.
+func NewRateLimiter(perSecond float64) *RateLimiter {
+	return &RateLimiter{
+		rate:    perSecond,
+		buckets: make(map[string]*tokenBucket),
+		now:     time.Now,
+	}
+}
+
+// Limit returns the number of tokens added to each bucket per second.
+func (l *RateLimiter) Limit() float64 {
+	return l.rate
+}
+
+// Allow reports whether a request for the database may proceed. If it may
+// not, the returned duration is how long until the bucket is no longer
+// overdrawn.
+func (l *RateLimiter) Allow(database string) (time.Duration, bool) {
+	l.mu.Lock()
+	defer l.mu.Unlock()
+
+	b := l.bucket(database)
+	if b.tokens > 0 {
+		return 0, true
+	}
+	return time.Duration((-b.tokens + 1) / l.rate * float64(time.Second)), false
+}
+
+// Charge removes n tokens from the database's bucket.
+func (l *RateLimiter) Charge(database string, n float64) {
+	l.mu.Lock()
+	l.bucket(database).tokens -= n
+	l.mu.Unlock()
+}
+
+// bucket returns the refilled bucket for the database. The lock must be held.
+func (l *RateLimiter) bucket(database string) *tokenBucket {
+	now := l.now()
+	l.sweep(now)
+
+	b, ok := l.buckets[database]
+	if !ok {
+		b = &tokenBucket{tokens: l.rate, last: now}
+		l.buckets[database] = b
+		return b
+	}
+
+	b.tokens = math.Min(l.rate, b.tokens+now.Sub(b.last).Seconds()*l.rate)
+	b.last = now
+	return b
+}
+
+// sweep removes buckets that have refilled completely, at most once per
+// rateLimiterSweepInterval. A full bucket behaves the same as a missing one.
+// The lock must be held.
+func (l *RateLimiter) sweep(now time.Time) {
+	if now.Sub(l.lastSweep) < rateLimiterSweepInterval {
+		return
+	}
+	l.lastSweep = now
+
+	for database, b := range l.buckets {
+		if b.tokens+now.Sub(b.last).Seconds()*l.rate >= l.rate {
+			delete(l.buckets, database)
+		}
+	}
+}
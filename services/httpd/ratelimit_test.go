@@ -0,0 +1,56 @@
+package httpd
+
+import (
+	"testing"
+	"time"
+)
+
+func TestRateLimiter(t *testing.T) {
+	now := time.Unix(0, 0)
+	l := NewRateLimiter(10)
+	l.now = func() time.Time { return now }
+
+	// A full bucket admits a request larger than the burst size.
+	if _, ok := l.Allow("db0"); !ok {
+		t.Fatal("expected request to be allowed")
+	}
+	l.Charge("db0", 25)
+
+	// The bucket is now overdrawn by 15 tokens and needs 1.6s to recover.
+	if wait, ok := l.Allow("db0"); ok {
+		t.Fatal("expected request to be rejected")
+	} else if wait != 1600*time.Millisecond {
+		t.Fatalf("unexpected wait: %s", wait)
+	}
+
+	// Other databases have their own bucket.
+	if _, ok := l.Allow("db1"); !ok {
+		t.Fatal("expected request for another database to be allowed")
+	}
+
+	now = now.Add(time.Second)
+	if _, ok := l.Allow("db0"); ok {
+		t.Fatal("expected request to be rejected while the bucket is overdrawn")
+	}
+
+	now = now.Add(time.Second)
+	if _, ok := l.Allow("db0"); !ok {
+		t.Fatal("expected request to be allowed after the bucket refilled")
+	}
+
+	// Buckets never hold more than one second's worth of tokens.
+	now = now.Add(time.Hour)
+	l.Charge("db0", 10)
+	if _, ok := l.Allow("db0"); ok {
+		t.Fatal("expected request to be rejected once the burst is spent")
+	}
+
+	// Buckets that have refilled are removed by the next sweep.
+	now = now.Add(rateLimiterSweepInterval)
+	l.Allow("db2")
+	if _, ok := l.buckets["db0"]; ok {
+		t.Fatal("expected idle bucket to be removed")
+	} else if len(l.buckets) != 1 {
+		t.Fatalf("unexpected number of buckets: %d", len(l.buckets))
+	}
+}
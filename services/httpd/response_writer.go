@@ -164,8 +164,13 @@ type csvFormatter struct {
 func (f *csvFormatter) WriteResponse(w io.Writer, resp Response) (err error) {
 	csv := csv.NewWriter(w)
 	if resp.Err != nil {
-		csv.Write([]string{"error"})
-		csv.Write([]string{resp.Err.Error()})
+		if resp.Code != "" {
+			csv.Write([]string{"error", "code"})
+			csv.Write([]string{resp.Err.Error(), string(resp.Code)})
+		} else {
+			csv.Write([]string{"error"})
+			csv.Write([]string{resp.Err.Error()})
+		}
 		csv.Flush()
 		return csv.Error()
 	}
@@ -276,12 +281,21 @@ func (f *msgpackFormatter) WriteResponse(w io.Writer, resp Response) (err error)
 	enc := msgp.NewWriter(w)
 	defer enc.Flush()
 
-	enc.WriteMapHeader(1)
 	if resp.Err != nil {
+		if resp.Code != "" {
+			enc.WriteMapHeader(2)
+		} else {
+			enc.WriteMapHeader(1)
+		}
 		enc.WriteString("error")
 		enc.WriteString(resp.Err.Error())
+		if resp.Code != "" {
+			enc.WriteString("code")
+			enc.WriteString(string(resp.Code))
+		}
 		return nil
 	} else {
+		enc.WriteMapHeader(1)
 		enc.WriteString("results")
 		enc.WriteArrayHeader(uint32(len(resp.Results)))
 		for _, result := range resp.Results {
@@ -234,6 +234,23 @@ func TestResponseWriter_MessagePack_Error(t *testing.T) {
 			if have := strings.TrimSpace(buf.String()); have != want {
 				t.Fatalf("unexpected output: %s != %s", have, want)
 			}
+
+			// The error code is included when set.
+			w = httptest.NewRecorder()
+			writer = httpd.NewResponseWriter(w, r)
+			writer.WriteResponse(httpd.Response{
+				Err:  fmt.Errorf("test error"),
+				Code: httpd.ErrorCodeParse,
+			})
+
+			buf.Reset()
+			if _, err := msgp.NewReader(w.Body).WriteToJSON(&buf); err != nil {
+				t.Fatalf("unexpected error: %s", err)
+			}
+			want = `{"error":"test error","code":"parse_error"}`
+			if have := strings.TrimSpace(buf.String()); have != want {
+				t.Fatalf("unexpected output: %s != %s", have, want)
+			}
 		})
 	}
 }
@@ -281,3 +298,23 @@ runtime,,amd64,8,darwin,go1.12
 		t.Errorf("unexpected output:\n\ngot=%v\nwant=%s", got, want)
 	}
 }
+
+func TestResponseWriter_CSV_Error(t *testing.T) {
+	header := make(http.Header)
+	header.Set("Accept", "text/csv")
+	r := &http.Request{
+		Header: header,
+		URL:    &url.URL{},
+	}
+	w := httptest.NewRecorder()
+
+	writer := httpd.NewResponseWriter(w, r)
+	writer.WriteResponse(httpd.Response{
+		Err:  fmt.Errorf("test error"),
+		Code: httpd.ErrorCodeParse,
+	})
+
+	if got, want := w.Body.String(), "error,code\ntest error,parse_error\n"; got != want {
+		t.Errorf("unexpected output:\n\ngot=%v\nwant=%s", got, want)
+	}
+}
@@ -44,6 +44,8 @@ const (
 	statPromReadRequest              = "promReadReq"            // Number of read requests to the prometheus endpoint.
 	statFluxQueryRequests            = "fluxQueryReq"           // Number of flux query requests served.
 	statFluxQueryRequestDuration     = "fluxQueryReqDurationNs" // Number of (wall-time) nanoseconds spent executing Flux query requests.
+	statRateLimitedRequests          = "rateLimitedReq"         // Number of requests rejected by per-database rate limits.
+	statPointsOutsideWindow          = "pointsOutsideWindow"    // Number of points rejected for being outside the write time window.
 
 )
 
@@ -197,12 +199,17 @@ func (s *Service) Open() error {
 
 // Close closes the underlying listener.
 func (s *Service) Close() error {
-	s.Handler.Close()
+	// Reject new writes while in-flight requests finish.
+	s.Handler.Drain()
 
-	ctx, cancel := context.WithTimeout(context.Background(), 10*time.Second)
+	ctx, cancel := context.WithTimeout(context.Background(), s.Handler.DrainTimeout())
 	defer cancel()
 
-	if err := s.httpServer.Shutdown(ctx); err != nil {
+	// The access and audit logs are closed only once in-flight requests have
+	// finished writing to them.
+	err := s.httpServer.Shutdown(ctx)
+	s.Handler.Close()
+	if err != nil {
 		return err
 	}
 
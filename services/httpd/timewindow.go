@@ -0,0 +1,87 @@
+package httpd
+
+import (
+	"fmt"
+	"sync/atomic"
+	"time"
+
+	"github.com/influxdata/influxdb/models"
+	"github.com/influxdata/influxdb/tsdb"
+)
+
+// writeTimeWindow bounds how far the timestamps of written points may be from
+// the server's clock. A zero bound is not enforced.
+type writeTimeWindow struct {
+	past   time.Duration
+	future time.Duration
+}
+
+func (w writeTimeWindow) enabled() bool {
+	return w.past > 0 || w.future > 0
+}
+
+// filter removes the points outside the window around now. The remaining
+// points keep their order and share the backing array of points. The number
+// of points removed is also returned.
+func (w writeTimeWindow) filter(points []models.Point, now time.Time) ([]models.Point, int) {
+	if !w.enabled() {
+		return points, 0
+	}
+
+	// Bounds beyond the range of nanosecond timestamps are clamped, since
+	// UnixNano is undefined for them.
+	minTime, maxTime := time.Unix(0, models.MinNanoTime), time.Unix(0, models.MaxNanoTime)
+	earliest, latest := int64(models.MinNanoTime), int64(models.MaxNanoTime)
+	if t := now.Add(-w.past); w.past > 0 && t.After(minTime) {
+		earliest = t.UnixNano()
+	}
+	if t := now.Add(w.future); w.future > 0 && t.Before(maxTime) {
+		latest = t.UnixNano()
+	}
+
+	kept := points[:0]
+	for _, p := range points {
+		if t := p.UnixNano(); t >= earliest && t <= latest {
+			kept = append(kept, p)
+		}
+	}
+	return kept, len(points) - len(kept)
+}
+
+// reason describes the points rejected by the window.
+func (w writeTimeWindow) reason() string {
+	switch {
+	case w.past > 0 && w.future > 0:
+		return fmt.Sprintf("points older than %s or more than %s in the future", w.past, w.future)
+	case w.past > 0:
+		return fmt.Sprintf("points older than %s", w.past)
+	default:
+		return fmt.Sprintf("points more than %s in the future", w.future)
+	}
+}
+
+// partialWriteError adds the points dropped by the window to err.
+func (w writeTimeWindow) partialWriteError(err tsdb.PartialWriteError, dropped int) tsdb.PartialWriteError {
+	if dropped == 0 {
+		return err
+	}
+
+	if err.Reason == "" {
+		err.Reason = w.reason()
+	} else {
+		err.Reason += "; " + w.reason()
+	}
+	err.Dropped += dropped
+	return err
+}
+
+// filterWriteWindow drops the points outside the configured write time window
+// and returns the remaining points with the number dropped.
+func (h *Handler) filterWriteWindow(points []models.Point, metrics dbWriteMetrics) ([]models.Point, int) {
+	points, dropped := h.writeWindow.filter(points, time.Now())
+	if dropped > 0 {
+		atomic.AddInt64(&h.stats.PointsOutsideWindow, int64(dropped))
+		metrics.rejected.Add(float64(dropped))
+	}
+	return points, dropped
+}
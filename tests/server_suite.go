@@ -37,7 +37,7 @@ func init() {
 			&Query{
 				name:    "create database should error with some unquoted names",
 				command: `CREATE DATABASE 0xdb0`,
-				exp:     `{"error":"error parsing query: found 0xdb0, expected identifier at line 1, char 17"}`,
+				exp:     `{"error":"error parsing query: found 0xdb0, expected identifier at line 1, char 17","code":"parse_error"}`,
 			},
 			&Query{
 				name:    "create database should error with invalid characters",
@@ -47,17 +47,17 @@ func init() {
 			&Query{
 				name:    "create database with retention duration should error with bad retention duration",
 				command: `CREATE DATABASE db0 WITH DURATION xyz`,
-				exp:     `{"error":"error parsing query: found xyz, expected duration at line 1, char 35"}`,
+				exp:     `{"error":"error parsing query: found xyz, expected duration at line 1, char 35","code":"parse_error"}`,
 			},
 			&Query{
 				name:    "create database with retention replication should error with bad retention replication number",
 				command: `CREATE DATABASE db0 WITH REPLICATION xyz`,
-				exp:     `{"error":"error parsing query: found xyz, expected integer at line 1, char 38"}`,
+				exp:     `{"error":"error parsing query: found xyz, expected integer at line 1, char 38","code":"parse_error"}`,
 			},
 			&Query{
 				name:    "create database with retention name should error with missing retention name",
 				command: `CREATE DATABASE db0 WITH NAME`,
-				exp:     `{"error":"error parsing query: found EOF, expected identifier at line 1, char 31"}`,
+				exp:     `{"error":"error parsing query: found EOF, expected identifier at line 1, char 31","code":"parse_error"}`,
 			},
 			&Query{
 				name:    "show database should succeed",
@@ -82,7 +82,7 @@ func init() {
 			&Query{
 				name:    "create database should error with bad retention duration",
 				command: `CREATE DATABASE db1 WITH DURATION xyz`,
-				exp:     `{"error":"error parsing query: found xyz, expected duration at line 1, char 35"}`,
+				exp:     `{"error":"error parsing query: found xyz, expected duration at line 1, char 35","code":"parse_error"}`,
 			},
 			&Query{
 				name:    "show database should succeed",
@@ -534,17 +534,17 @@ func TestServer_UserCommands(t *testing.T) {
 			&Query{
 				name:    "bad create user request",
 				command: `CREATE USER 0xBAD WITH PASSWORD pwd1337`,
-				exp:     `{"error":"error parsing query: found 0xBAD, expected identifier at line 1, char 13"}`,
+				exp:     `{"error":"error parsing query: found 0xBAD, expected identifier at line 1, char 13","code":"parse_error"}`,
 			},
 			&Query{
 				name:    "bad create user request, no name",
 				command: `CREATE USER WITH PASSWORD pwd1337`,
-				exp:     `{"error":"error parsing query: found WITH, expected identifier at line 1, char 13"}`,
+				exp:     `{"error":"error parsing query: found WITH, expected identifier at line 1, char 13","code":"parse_error"}`,
 			},
 			&Query{
 				name:    "bad create user request, no password",
 				command: `CREATE USER jdoe`,
-				exp:     `{"error":"error parsing query: found EOF, expected WITH at line 1, char 18"}`,
+				exp:     `{"error":"error parsing query: found EOF, expected WITH at line 1, char 18","code":"parse_error"}`,
 			},
 			&Query{
 				name:    "drop user",
@@ -10119,7 +10119,7 @@ Table: keys: [_field, _measurement, k]
 
 	// Test a broken flux query - we get back both the http status code and the underlying flux error
 	buf.Reset()
-	assert.EqualError(t, c.ExecuteFluxQuery(buf, `v1.databases()`), "{\"error\":\"error @1:1-1:3: undefined identifier v1\"}")
+	assert.EqualError(t, c.ExecuteFluxQuery(buf, `v1.databases()`), "{\"error\":\"error @1:1-1:3: undefined identifier v1\",\"code\":\"invalid\"}")
 	assert.Equal(t, "", buf.String())
 }
 